@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+
+use crate::image_io::{self, ExrLayer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    // linear depth along the view direction, 0 where nothing was hit
+    Depth,
+    Normal,
+    Albedo,
+    // index of the object under the pixel centre, -1 for the background
+    ObjectId,
+    // in pixels, x right and y down, from where the surface was seen in the
+    // previous frame, from the motion of the camera
+    Motion,
+    // light that scattered once on the way to the camera
+    Direct,
+    // light that scattered more than once
+    Indirect,
+    // lights and the background seen directly
+    Emission,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::Motion,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::Emission,
+    ];
+
+    // layer name in the exr, and part of the file name of separate files
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::Motion => "motion",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Emission => "emission",
+        }
+    }
+
+    fn channels(self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::Normal => &["X", "Y", "Z"],
+            Aov::ObjectId => &["id"],
+            Aov::Motion => &["X", "Y"],
+            Aov::Albedo | Aov::Direct | Aov::Indirect | Aov::Emission => &["R", "G", "B"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AovFormat {
+    // every aov and the beauty pass as layers of `<name>.exr`
+    MultiLayer,
+    // `<name>.<aov>.exr` for every aov and `<name>.beauty.exr`
+    Separate,
+}
+
+impl AovFormat {
+    pub const ALL: [AovFormat; 2] = [AovFormat::MultiLayer, AovFormat::Separate];
+
+    // for the command line
+    pub fn config_name(self) -> &'static str {
+        match self {
+            AovFormat::MultiLayer => "multilayer",
+            AovFormat::Separate => "separate",
+        }
+    }
+
+    pub fn from_config_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.config_name() == name)
+    }
+}
+
+// what the samples of a pixel saw, the renderer keeps weighted sums of them
+// next to the beauty accumulation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AovSample {
+    pub depth: f32,
+    pub normal: glam::Vec3,
+    pub albedo: glam::Vec3,
+    pub motion: glam::Vec2,
+    pub direct: glam::Vec3,
+    pub indirect: glam::Vec3,
+    pub emission: glam::Vec3,
+    // number of samples summed
+    pub weight: f32,
+}
+
+impl AovSample {
+    // `weight` is the filter weight of the sample, the samples are counted
+    pub fn accumulate(&mut self, sample: &AovSample, weight: f32) {
+        self.depth += sample.depth * weight;
+        self.normal += sample.normal * weight;
+        self.albedo += sample.albedo * weight;
+        self.motion += sample.motion * weight;
+        self.direct += sample.direct * weight;
+        self.indirect += sample.indirect * weight;
+        self.emission += sample.emission * weight;
+        self.weight += 1.0;
+    }
+
+    // the average, the lighting scaled by `exposure`
+    pub fn resolve(&self, exposure: f32) -> AovSample {
+        if self.weight <= 0.0 {
+            return AovSample::default();
+        }
+
+        let scale = 1.0 / self.weight;
+
+        AovSample {
+            depth: self.depth * scale,
+            normal: self.normal * scale,
+            albedo: self.albedo * scale,
+            motion: self.motion * scale,
+            direct: self.direct * scale * exposure,
+            indirect: self.indirect * scale * exposure,
+            emission: self.emission * scale * exposure,
+            weight: 1.0,
+        }
+    }
+}
+
+// resolved buffers of a render, rows top to bottom
+pub struct AovImage {
+    pub width: usize,
+    pub height: usize,
+    // linear, exposed but not tone mapped
+    pub beauty: Vec<glam::Vec3>,
+    pub samples: Vec<AovSample>,
+    pub object_ids: Vec<f32>,
+}
+
+impl AovImage {
+    // next to `path`, which names the beauty image
+    pub fn write(&self, path: &Path, format: AovFormat) -> std::io::Result<()> {
+        let beauty = ExrLayer {
+            name: "beauty",
+            channels: rgb_channels(&self.beauty),
+        };
+
+        match format {
+            AovFormat::MultiLayer => {
+                let mut layers = vec![beauty];
+                layers.extend(Aov::ALL.map(|aov| self.layer(aov)));
+
+                image_io::write_exr(path.with_extension("exr"), self.width, self.height, layers)
+            }
+            AovFormat::Separate => {
+                image_io::write_exr(
+                    sibling_path(path, "beauty"),
+                    self.width,
+                    self.height,
+                    vec![beauty],
+                )?;
+
+                for aov in Aov::ALL {
+                    image_io::write_exr(
+                        sibling_path(path, aov.name()),
+                        self.width,
+                        self.height,
+                        vec![self.layer(aov)],
+                    )?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn layer(&self, aov: Aov) -> ExrLayer {
+        let samples = &self.samples;
+
+        let channels = match aov {
+            Aov::Depth => vec![samples.iter().map(|s| s.depth).collect()],
+            Aov::ObjectId => vec![self.object_ids.clone()],
+            Aov::Motion => vec![
+                samples.iter().map(|s| s.motion.x).collect(),
+                samples.iter().map(|s| s.motion.y).collect(),
+            ],
+            Aov::Normal => channels_of(samples, |s| s.normal),
+            Aov::Albedo => channels_of(samples, |s| s.albedo),
+            Aov::Direct => channels_of(samples, |s| s.direct),
+            Aov::Indirect => channels_of(samples, |s| s.indirect),
+            Aov::Emission => channels_of(samples, |s| s.emission),
+        };
+
+        ExrLayer {
+            name: aov.name(),
+            channels: aov.channels().iter().copied().zip(channels).collect(),
+        }
+    }
+}
+
+fn channels_of(samples: &[AovSample], value: impl Fn(&AovSample) -> glam::Vec3) -> Vec<Vec<f32>> {
+    (0..3)
+        .map(|channel| samples.iter().map(|s| value(s)[channel]).collect())
+        .collect()
+}
+
+fn rgb_channels(pixels: &[glam::Vec3]) -> Vec<(&'static str, Vec<f32>)> {
+    ["R", "G", "B"]
+        .into_iter()
+        .enumerate()
+        .map(|(channel, name)| (name, pixels.iter().map(|p| p[channel]).collect()))
+        .collect()
+}
+
+// `render.ppm` becomes `render.<suffix>.exr`
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{suffix}.exr"))
+}
@@ -3,7 +3,15 @@ use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
 
-use crate::camera::Camera;
+use crate::aov::AovFormat;
+use crate::camera::{Bookmark, PhysicalCamera};
+use crate::camera::{Camera, ControllerMode};
+use crate::camera_path::Keyframe;
+use crate::controls::{Action, Controls};
+use crate::gizmo::Gizmo;
+use crate::headless::HeadlessOptions;
+use crate::history::History;
+use crate::ray::Ray;
 use crate::renderer::Renderer;
 use crate::world::*;
 use crate::Program;
@@ -21,9 +29,25 @@ pub struct Application {
     program: Arc<Program>,
     renderer: Arc<Mutex<Renderer>>,
     last_rect: egui::Rect,
+    // size of the image last uploaded to the texture
+    texture_size: glam::UVec2,
     gpu_time: Arc<Mutex<Duration>>,
     clock: Instant,
     world: World,
+    picking_focus: bool,
+    // index into the objects of the world
+    selection: Option<usize>,
+    gizmo: Gizmo,
+    history: History,
+    // set by everything that edits the world during the frame
+    world_edited: bool,
+    bookmark_name: String,
+    // playback time along the camera path while it is playing
+    path_playback: Option<f32>,
+    export_status: Arc<Mutex<String>>,
+    // where the controls are saved on exit, `None` when the file there failed
+    // to load
+    controls_path: Option<std::path::PathBuf>,
 }
 
 impl Application {
@@ -133,50 +157,377 @@ impl Application {
         )
         .unwrap();
 
-        let objects = vec![
-            Sphere {
-                position: glam::vec3(0.0, 0.0, 0.0),
-                radius: 0.5,
-                albedo: glam::vec3(1.0, 0.0, 1.0),
-            },
-            Sphere {
-                position: glam::vec3(-0.2, -0.3, -3.0),
-                radius: 2.0,
-                albedo: glam::vec3(1.0, 0.53, 0.0),
-            },
-        ];
+        let mut camera = Camera::new(45.0_f32.to_radians(), 0.1, 100.0);
+
+        // a file that failed to parse is not saved over, it is left for the
+        // user to fix
+        let controls_path = crate::controls::config_path();
+        let save_controls = match Controls::load(&controls_path) {
+            Ok(controls) => {
+                camera.controls = controls;
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(e) => {
+                eprintln!("failed to load {}: {e}", controls_path.display());
+                false
+            }
+        };
+
+        let world = World::demo();
 
         Self {
-            camera: Camera::new(45.0_f32.to_radians(), 0.1, 100.0),
+            camera,
             clock: Instant::now(),
             texture_id,
             vao,
             program: Arc::new(program),
             renderer: Arc::new(Renderer::new().into()),
             last_rect: egui::Rect::ZERO,
+            texture_size: glam::UVec2::ZERO,
             gpu_time: Arc::new(Duration::ZERO.into()),
-            world: World { objects },
+            history: History::new(&world),
+            world_edited: false,
+            world,
+            picking_focus: false,
+            selection: None,
+            gizmo: Gizmo::default(),
+            bookmark_name: String::new(),
+            path_playback: None,
+            export_status: Arc::new(Mutex::new(String::new())),
+            controls_path: save_controls.then_some(controls_path),
+        }
+    }
+
+    // casts a ray through the cursor into the scene
+    fn pick(&self, pointer_pos: Option<egui::Pos2>, rect: egui::Rect) -> Option<(Ray, Hit)> {
+        let pos = pointer_pos?;
+
+        let point = glam::vec2(pos.x - rect.min.x, pos.y - rect.min.y);
+        let ray = self.camera.get_viewport_ray(point)?;
+        let hit = self.world.intersect(&ray, 0.0, f32::INFINITY)?;
+
+        Some((ray, hit))
+    }
+
+    fn focus_at(&mut self, pointer_pos: Option<egui::Pos2>, rect: egui::Rect) {
+        if let Some((ray, hit)) = self.pick(pointer_pos, rect) {
+            self.camera.lens.focal_distance =
+                (hit.position - ray.origin).dot(*self.camera.get_direction());
+            self.renderer.lock().unwrap().reset_frame_index();
+        }
+    }
+
+    fn select_at(&mut self, pointer_pos: Option<egui::Pos2>, rect: egui::Rect) {
+        self.selection = self
+            .pick(pointer_pos, rect)
+            .map(|(_, hit)| hit.object_index);
+    }
+
+    // the selected object, or the whole scene when nothing is selected
+    fn frame_selection(&mut self) {
+        let bounds = match self.selection {
+            Some(object) => {
+                let sphere = &self.world.objects[object];
+                Some((sphere.position, sphere.radius))
+            }
+            None => self.world.get_bounds(),
+        };
+
+        if let Some((center, radius)) = bounds {
+            self.camera.frame(center, radius);
+            self.renderer.lock().unwrap().reset_frame_index();
+        }
+    }
+
+    fn history_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("History")
+            .default_pos((680.0, 420.0))
+            .default_open(false)
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let mut jump_to = None;
+
+                ui.horizontal(|ui| {
+                    let position = self.history.get_position();
+
+                    if ui
+                        .add_enabled(self.history.can_undo(), egui::Button::new("Undo"))
+                        .clicked()
+                    {
+                        jump_to = Some(position - 1);
+                    }
+
+                    if ui
+                        .add_enabled(self.history.can_redo(), egui::Button::new("Redo"))
+                        .clicked()
+                    {
+                        jump_to = Some(position + 1);
+                    }
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        let position = self.history.get_position();
+
+                        if ui
+                            .selectable_label(position == 0, "Initial scene")
+                            .clicked()
+                        {
+                            jump_to = Some(0);
+                        }
+
+                        for (i, command) in self.history.get_commands().iter().enumerate() {
+                            // undone commands stay in the list until something new is done
+                            let text = egui::RichText::new(command.name);
+                            let text = if i < position { text } else { text.weak() };
+
+                            if ui.selectable_label(i + 1 == position, text).clicked() {
+                                jump_to = Some(i + 1);
+                            }
+                        }
+                    });
+
+                if let Some(position) = jump_to {
+                    self.history.jump_to(position, &mut self.world);
+                    self.world_replaced();
+                }
+            });
+    }
+
+    // after undo and redo swapped the world for another state
+    fn world_replaced(&mut self) {
+        if self
+            .selection
+            .is_some_and(|object| object >= self.world.objects.len())
+        {
+            self.selection = None;
+        }
+
+        // a drag doesn't carry over to another state of the object
+        self.gizmo.reset();
+        self.renderer.lock().unwrap().reset_frame_index();
+    }
+
+    fn views_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Views")
+            .default_pos((680.0, 10.0))
+            .default_open(false)
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let mut jump_to = None;
+                let mut remove = None;
+
+                for (i, bookmark) in self.world.bookmarks.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button(&bookmark.name).clicked() {
+                            jump_to = Some(bookmark.viewpoint);
+                        }
+
+                        if ui.small_button("x").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+
+                if let Some(i) = remove {
+                    self.world.bookmarks.remove(i);
+                    self.world_edited = true;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.bookmark_name);
+
+                    if ui.button("Save view").clicked() {
+                        let name = match self.bookmark_name.trim() {
+                            "" => format!("View {}", self.world.bookmarks.len() + 1),
+                            name => name.to_string(),
+                        };
+
+                        self.world.bookmarks.push(Bookmark {
+                            name,
+                            viewpoint: self.camera.get_viewpoint(),
+                        });
+                        self.bookmark_name.clear();
+                        self.world_edited = true;
+                    }
+                });
+
+                ui.separator();
+                ui.label("Camera path");
+
+                let path = &mut self.world.camera_path;
+                let mut remove = None;
+                let mut retime = None;
+
+                egui::ScrollArea::vertical()
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        for (i, keyframe) in path.get_keyframes().iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let mut time = keyframe.time;
+                                if ui
+                                    .add(
+                                        egui::DragValue::new(&mut time)
+                                            .range(0.0..=f32::MAX)
+                                            .speed(0.05)
+                                            .suffix(" s"),
+                                    )
+                                    .changed()
+                                {
+                                    retime = Some((i, time));
+                                }
+
+                                if ui.button("Go").clicked() {
+                                    jump_to = Some(keyframe.viewpoint);
+                                }
+
+                                if ui.small_button("x").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                        }
+                    });
+
+                if let Some((i, time)) = retime {
+                    path.set_time(i, time);
+                    self.world_edited = true;
+                }
+
+                if let Some(i) = remove {
+                    path.remove(i);
+                    self.world_edited = true;
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Add keyframe").clicked() {
+                        let time = if path.is_empty() {
+                            0.0
+                        } else {
+                            path.get_duration() + 1.0
+                        };
+
+                        path.insert(Keyframe {
+                            time,
+                            viewpoint: self.camera.get_viewpoint(),
+                        });
+                        self.world_edited = true;
+                    }
+
+                    let playing = self.path_playback.is_some();
+                    if ui
+                        .add_enabled(
+                            !path.is_empty(),
+                            egui::Button::new(if playing { "Stop" } else { "Play" }),
+                        )
+                        .clicked()
+                    {
+                        self.path_playback = if playing { None } else { Some(0.0) };
+                    }
+                });
+
+                let status = self.export_status.lock().unwrap().clone();
+                // the export thread holds on to the status until it is done
+                let exporting = Arc::strong_count(&self.export_status) > 1;
+
+                if ui
+                    .add_enabled(
+                        !path.is_empty() && !exporting,
+                        egui::Button::new("Export frames"),
+                    )
+                    .clicked()
+                {
+                    self.export_path();
+                }
+
+                if !status.is_empty() {
+                    ui.label(status);
+                }
+
+                if let Some(viewpoint) = jump_to {
+                    self.path_playback = None;
+                    self.camera.set_viewpoint(viewpoint);
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+            });
+    }
+
+    // renders the camera path in the background at the viewport size
+    fn export_path(&mut self) {
+        let camera = self.camera.clone();
+        let world = self.world.clone();
+        let settings = self.renderer.lock().unwrap().settings;
+
+        let options = HeadlessOptions {
+            width: self.last_rect.width().max(1.0) as u32,
+            height: self.last_rect.height().max(1.0) as u32,
+            path: true,
+            aovs: settings.aovs.then_some(AovFormat::MultiLayer),
+            output: "frames".into(),
+            ..Default::default()
+        };
+        let status = Arc::clone(&self.export_status);
+
+        std::thread::spawn(move || {
+            let result =
+                crate::headless::render_sequence(camera, &world, settings, &options, |i, n| {
+                    *status.lock().unwrap() = format!("Exporting frame {}/{n}", i + 1);
+                });
+
+            *status.lock().unwrap() = match result {
+                Ok(()) => format!("Exported to {}", options.output.display()),
+                Err(e) => format!("Export failed: {e}"),
+            };
+        });
+    }
+
+    fn advance_playback(&mut self, dt: f32) {
+        let Some(time) = self.path_playback.as_mut() else {
+            return;
+        };
+
+        *time += dt;
+
+        if *time > self.world.camera_path.get_duration() {
+            self.path_playback = None;
+            return;
+        }
+
+        if let Some(viewpoint) = self
+            .world
+            .camera_path
+            .evaluate(*time, self.camera.get_up_direction())
+        {
+            self.camera.set_viewpoint(viewpoint);
+            self.renderer.lock().unwrap().camera_moved();
         }
     }
 
+    // `size` is the size of the traced image, which may be smaller than the
+    // viewport it is stretched over
     unsafe fn update_texture(
         gl: &glow::Context,
         texture_id: glow::NativeTexture,
         image_vec: &[u32],
-        old_rect: egui::Rect,
-        rect: egui::Rect,
+        old_size: glam::UVec2,
+        size: glam::UVec2,
     ) {
         let data = std::slice::from_raw_parts(
             image_vec.as_ptr() as *const u8,
             std::mem::size_of_val(image_vec),
         );
 
-        let w = rect.max.x - rect.min.x;
-        let h = rect.max.y - rect.min.y;
+        let (w, h) = (size.x, size.y);
 
         gl.bind_texture(glow::TEXTURE_2D, Some(texture_id));
 
-        if old_rect == rect {
+        if old_size == size {
             gl.tex_sub_image_2d(
                 glow::TEXTURE_2D,
                 0,
@@ -209,24 +560,63 @@ impl eframe::App for Application {
         let dt = self.clock.elapsed().as_nanos() as f32 / 1e9;
         self.clock = Instant::now();
 
+        // the camera only takes the pointer over the viewport, the panels
+        // around it and the windows on top of it keep it for themselves
+        let in_viewport = |pos: egui::Pos2| {
+            self.last_rect.contains(pos)
+                && ctx
+                    .layer_id_at(pos)
+                    .is_none_or(|layer| layer.order == egui::Order::Background)
+        };
+        let over_viewport = ctx.pointer_hover_pos().is_some_and(in_viewport);
+        // a drag keeps the camera even when it leaves the viewport
+        let pressed_in_viewport = ctx.input(|i| i.pointer.press_origin().is_some_and(in_viewport));
+
         let pointer_state = ctx.input(|i| crate::PointerState {
             pos: i
                 .pointer
                 .hover_pos()
                 .map(|egui::Pos2 { x, y }| glam::vec2(x, y)),
-            secondary_down: i.pointer.secondary_down(),
+            secondary_down: pressed_in_viewport && i.pointer.secondary_down(),
+            middle_down: pressed_in_viewport && i.pointer.middle_down(),
+            scroll: if over_viewport {
+                i.smooth_scroll_delta.y
+            } else {
+                0.0
+            },
         });
 
-        let keyboard_state = ctx.input(|i| crate::KeyboardState {
-            w: i.keys_down.contains(&egui::Key::W),
-            a: i.keys_down.contains(&egui::Key::A),
-            s: i.keys_down.contains(&egui::Key::S),
-            d: i.keys_down.contains(&egui::Key::D),
-            q: i.keys_down.contains(&egui::Key::Q),
-            e: i.keys_down.contains(&egui::Key::E),
+        // text fields get the keyboard to themselves
+        let typing = ctx.wants_keyboard_input();
+        let bindings = self.camera.controls.bindings;
+        let keyboard_state = ctx.input(|i| {
+            let down = |action| !typing && i.keys_down.contains(&bindings.get(action));
+
+            crate::KeyboardState {
+                forward: down(Action::Forward),
+                backward: down(Action::Backward),
+                left: down(Action::Left),
+                right: down(Action::Right),
+                down: down(Action::Down),
+                up: down(Action::Up),
+                roll_left: down(Action::RollLeft),
+                roll_right: down(Action::RollRight),
+                boost: i.modifiers.shift,
+                slow: i.modifiers.ctrl,
+            }
         });
 
-        self.camera.update(dt, pointer_state, keyboard_state);
+        if self.camera.update(dt, pointer_state, keyboard_state) {
+            self.path_playback = None;
+            self.renderer.lock().unwrap().camera_moved();
+        }
+
+        self.advance_playback(dt);
+
+        if !typing && ctx.input(|i| i.key_pressed(bindings.get(Action::Frame))) {
+            self.frame_selection();
+        }
+
         if pointer_state.secondary_down {
             ctx.set_cursor_icon(egui::CursorIcon::None);
         } else {
@@ -235,24 +625,213 @@ impl eframe::App for Application {
 
         ctx.set_pixels_per_point(1.0);
 
+        if !typing {
+            // checked first, the plain shortcut also matches with shift held
+            let redo = egui::KeyboardShortcut::new(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Z,
+            );
+            let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+
+            if ctx.input_mut(|i| i.consume_shortcut(&redo)) {
+                if self.history.redo(&mut self.world) {
+                    self.world_replaced();
+                }
+            } else if ctx.input_mut(|i| i.consume_shortcut(&undo))
+                && self.history.undo(&mut self.world)
+            {
+                self.world_replaced();
+            }
+        }
+
+        self.history_window(ctx);
+
+        // everything after this that edits the world reports it here, the
+        // history only looks at the world then
+        self.world_edited = false;
+
         egui::Window::new("Info")
             .default_pos((10.0, 10.0))
             .collapsible(true)
             .resizable(true)
             .show(ctx, |ui| {
                 let gpu_time = *self.gpu_time.lock().unwrap();
-                let render_time = self.renderer.lock().unwrap().render_time;
+                let mut renderer = self.renderer.lock().unwrap();
+                let render_time = renderer.render_time;
 
                 ui.label(format!("GPU time: {gpu_time:?}"));
                 ui.label(format!("Render time: {render_time:?}",));
                 ui.separator();
                 ui.label(format!("Frame time: {:?}", gpu_time + render_time));
+                ui.separator();
+                ui.label(format!("Samples: {}", renderer.get_frame_index()));
+
+                let size = renderer.get_size();
+                ui.label(format!("Resolution: {}x{}", size.x, size.y));
+                ui.label(match self.selection {
+                    Some(object) => format!("Selected: object {object}"),
+                    None => "Selected: nothing".to_string(),
+                });
+
+                if ui.button("Reset").clicked() {
+                    renderer.reset_frame_index();
+                }
+            });
+
+        egui::Window::new("Render settings")
+            .default_pos((10.0, 220.0))
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                // the renderer notices the changes that need a new accumulation
+                crate::ui::render_settings(ui, &mut self.renderer.lock().unwrap().settings);
+
+                ui.separator();
+
+                ui.collapsing("Background", |ui| {
+                    if crate::ui::background_settings(ui, &mut self.world.background) {
+                        self.world_edited = true;
+                        self.renderer.lock().unwrap().reset_frame_index();
+                    }
+                });
+            });
+
+        egui::Window::new("Camera")
+            .default_pos((10.0, 420.0))
+            .collapsible(true)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Controls");
+                    ui.radio_value(&mut self.camera.controller_mode, ControllerMode::Fly, "Fly");
+                    ui.radio_value(
+                        &mut self.camera.controller_mode,
+                        ControllerMode::Orbit,
+                        "Orbit",
+                    );
+                });
+
+                let frame_key = self.camera.controls.bindings.get(Action::Frame);
+                let frame_target = if self.selection.is_some() {
+                    "selection"
+                } else {
+                    "scene"
+                };
+
+                if ui
+                    .button(format!("Frame {frame_target} ({})", frame_key.name()))
+                    .clicked()
+                {
+                    self.frame_selection();
+                }
+
+                ui.separator();
+
+                let mut roll = self.camera.get_roll().to_degrees();
+                if ui
+                    .add(egui::Slider::new(&mut roll, -180.0..=180.0).text("Roll"))
+                    .changed()
+                {
+                    self.camera.set_roll(roll.to_radians());
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+
+                let mut up = self.camera.get_up_direction();
+                if crate::ui::up_direction_edit(ui, &mut up) {
+                    self.camera.set_up_direction(up);
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+
+                ui.separator();
+
+                let mut projection = self.camera.get_projection_mode();
+                if crate::ui::projection_settings(ui, &mut projection) {
+                    self.camera.set_projection_mode(projection);
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+
+                ui.separator();
+
+                let mut physical = self.camera.get_physical();
+                let mut enabled = physical.is_some();
+                let mut changed = ui.checkbox(&mut enabled, "Physical camera").changed();
+
+                if enabled != physical.is_some() {
+                    physical = enabled.then(|| {
+                        // start from the current field of view
+                        let mut physical = PhysicalCamera::default();
+                        physical.focal_length =
+                            physical.focal_length_for(self.camera.get_viewpoint().vfov);
+                        physical
+                    });
+                }
+
+                if let Some(physical) = &mut physical {
+                    changed |= crate::ui::physical_camera_settings(ui, physical);
+                }
+
+                if changed {
+                    self.camera.set_physical(physical);
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+
+                ui.separator();
+
+                if crate::ui::lens_settings(ui, &mut self.camera.lens, physical.is_some()) {
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+
+                ui.separator();
+
+                let mut stereo = self.camera.get_stereo();
+                if crate::ui::stereo_settings(ui, &mut stereo) {
+                    self.camera.set_stereo(stereo);
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+
+                ui.separator();
+
+                if crate::ui::shutter_settings(ui, &mut self.camera.shutter) {
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
+
+                ui.toggle_value(&mut self.picking_focus, "Click to focus");
+
+                ui.separator();
+
+                ui.collapsing("Controls", |ui| {
+                    crate::ui::controls_settings(ui, &mut self.camera.controls);
+                });
+            });
+
+        self.views_window(ctx);
+
+        egui::SidePanel::right("inspector")
+            .resizable(true)
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                ui.collapsing("Gizmo", |ui| {
+                    crate::ui::gizmo_settings(ui, &mut self.gizmo);
+                });
+
+                ui.separator();
+
+                let spawn_position = self.camera.get_pivot();
+                if crate::inspector::inspector(
+                    ui,
+                    &mut self.world,
+                    &mut self.selection,
+                    spawn_position,
+                ) {
+                    self.world_edited = true;
+                    self.renderer.lock().unwrap().reset_frame_index();
+                }
             });
 
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::Frame::canvas(ui.style()).show(ui, |ui| {
-                let (rect, _response) =
-                    ui.allocate_exact_size(ui.available_size(), egui::Sense::drag());
+                let (rect, response) =
+                    ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
 
                 let old_rect = self.last_rect;
 
@@ -265,15 +844,44 @@ impl eframe::App for Application {
 
                 self.last_rect = rect;
 
+                if self.picking_focus {
+                    ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+
+                    if response.clicked() {
+                        self.focus_at(response.interact_pointer_pos(), rect);
+                        self.picking_focus = false;
+                    }
+                } else {
+                    if let Some(object) = self.selection {
+                        let sphere = &mut self.world.objects[object];
+                        if self.gizmo.update(&response, &self.camera, sphere) {
+                            self.world_edited = true;
+                            self.renderer.lock().unwrap().reset_frame_index();
+                        }
+                    } else {
+                        self.gizmo.reset();
+                    }
+
+                    if response.clicked() && !self.gizmo.is_active() {
+                        self.select_at(response.interact_pointer_pos(), rect);
+                    }
+                }
+
                 let program = Arc::clone(&self.program);
                 let vao = self.vao;
                 let texture_id = self.texture_id;
                 let gpu_time = Arc::clone(&self.gpu_time);
 
-                self.renderer
-                    .lock()
-                    .unwrap()
-                    .render(rect, &self.camera, &self.world);
+                let old_size = self.texture_size;
+
+                {
+                    let mut renderer = self.renderer.lock().unwrap();
+                    renderer.selection = self.selection;
+                    renderer.render(rect, &self.camera, &self.world);
+                    self.texture_size = renderer.get_size();
+                }
+
+                let size = self.texture_size;
                 let renderer = Arc::clone(&self.renderer);
 
                 let callback = egui::PaintCallback {
@@ -289,8 +897,8 @@ impl eframe::App for Application {
                                     gl,
                                     texture_id,
                                     renderer.lock().unwrap().get_texture_data(),
-                                    old_rect,
-                                    rect,
+                                    old_size,
+                                    size,
                                 )
                             }
 
@@ -312,9 +920,36 @@ impl eframe::App for Application {
                 };
 
                 ui.painter().add(callback);
+
+                if let Some(object) = self.selection {
+                    self.gizmo.draw(
+                        &ui.painter_at(rect),
+                        rect,
+                        &self.camera,
+                        &self.world.objects[object],
+                    );
+                }
             });
         });
 
+        let continuous = typing || ctx.input(|i| i.pointer.any_down());
+
+        if self.world_edited {
+            self.history.record(&self.world, continuous);
+        } else {
+            self.history.idle(continuous);
+        }
+
         ctx.request_repaint();
     }
+
+    fn on_exit(&mut self, _gl: Option<&glow::Context>) {
+        let Some(path) = &self.controls_path else {
+            return;
+        };
+
+        if let Err(e) = self.camera.controls.save(path) {
+            eprintln!("failed to save {}: {e}", path.display());
+        }
+    }
 }
@@ -0,0 +1,290 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::environment::EnvironmentMap;
+use crate::sampling;
+
+// angular radius of the sun disc as seen from the ground
+const SUN_ANGULAR_RADIUS: f32 = 0.00465;
+const SUN_RADIANCE: f32 = 40000.0;
+
+// brings the preetham luminance (kcd/m^2) into the range of the other modes
+const SKY_LUMINANCE_SCALE: f32 = 0.05;
+
+#[derive(Debug, Clone)]
+pub enum BackgroundMode {
+    Solid {
+        color: glam::Vec3,
+    },
+    Gradient {
+        zenith: glam::Vec3,
+        horizon: glam::Vec3,
+        ground: glam::Vec3,
+    },
+    Sky(PhysicalSky),
+    Environment(Arc<EnvironmentMap>),
+}
+
+// environment maps are compared by identity, going through every pixel to
+// find out whether anything changed would be too slow
+impl PartialEq for BackgroundMode {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BackgroundMode::Solid { color: a }, BackgroundMode::Solid { color: b }) => a == b,
+            (
+                BackgroundMode::Gradient {
+                    zenith: a_zenith,
+                    horizon: a_horizon,
+                    ground: a_ground,
+                },
+                BackgroundMode::Gradient {
+                    zenith: b_zenith,
+                    horizon: b_horizon,
+                    ground: b_ground,
+                },
+            ) => a_zenith == b_zenith && a_horizon == b_horizon && a_ground == b_ground,
+            (BackgroundMode::Sky(a), BackgroundMode::Sky(b)) => a == b,
+            (BackgroundMode::Environment(a), BackgroundMode::Environment(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Background {
+    pub mode: BackgroundMode,
+    // rotation around the world up axis, in radians
+    pub rotation: f32,
+    pub intensity: f32,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self {
+            mode: BackgroundMode::Gradient {
+                zenith: glam::vec3(0.35, 0.55, 0.9),
+                horizon: glam::vec3(0.8, 0.85, 0.9),
+                ground: glam::vec3(0.2, 0.18, 0.16),
+            },
+            rotation: 0.0,
+            intensity: 0.5,
+        }
+    }
+}
+
+impl Background {
+    pub fn is_black(&self) -> bool {
+        match &self.mode {
+            _ if self.intensity <= 0.0 => true,
+            BackgroundMode::Solid { color } => *color == glam::Vec3::ZERO,
+            BackgroundMode::Environment(map) => map.is_black(),
+            _ => false,
+        }
+    }
+
+    pub fn radiance(&self, direction: glam::Vec3) -> glam::Vec3 {
+        let direction = self.rotate_to_local(direction);
+
+        let radiance = match &self.mode {
+            BackgroundMode::Solid { color } => *color,
+            BackgroundMode::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => {
+                if direction.y >= 0.0 {
+                    horizon.lerp(*zenith, direction.y.sqrt())
+                } else {
+                    horizon.lerp(*ground, (-direction.y * 4.0).min(1.0))
+                }
+            }
+            BackgroundMode::Sky(sky) => sky.radiance(direction),
+            BackgroundMode::Environment(map) => map.radiance(direction),
+        };
+
+        radiance * self.intensity
+    }
+
+    // returns the sampled world space direction, the radiance arriving from
+    // it and the solid angle pdf of having picked it
+    pub fn sample(&self, rng: &mut fastrand::Rng) -> (glam::Vec3, glam::Vec3, f32) {
+        let u = sampling::random_vec2(rng);
+
+        let local = match &self.mode {
+            BackgroundMode::Sky(sky) if rng.bool() => sky.sample_sun(u),
+            BackgroundMode::Environment(map) => map.sample(u),
+            _ => sampling::uniform_sphere(u),
+        };
+
+        let direction = self.rotate_to_world(local);
+        (direction, self.radiance(direction), self.pdf(direction))
+    }
+
+    pub fn pdf(&self, direction: glam::Vec3) -> f32 {
+        let uniform = sampling::uniform_sphere_pdf();
+
+        match &self.mode {
+            BackgroundMode::Sky(sky) => {
+                0.5 * uniform + 0.5 * sky.sun_pdf(self.rotate_to_local(direction))
+            }
+            BackgroundMode::Environment(map) => map.pdf(self.rotate_to_local(direction)),
+            _ => uniform,
+        }
+    }
+
+    fn rotate_to_local(&self, direction: glam::Vec3) -> glam::Vec3 {
+        glam::Quat::from_rotation_y(-self.rotation) * direction
+    }
+
+    fn rotate_to_world(&self, direction: glam::Vec3) -> glam::Vec3 {
+        glam::Quat::from_rotation_y(self.rotation) * direction
+    }
+}
+
+// Preetham, Shirley and Smits 1999, "A Practical Analytic Model for Daylight"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalSky {
+    sun_direction: glam::Vec3,
+    turbidity: f32,
+
+    // cached, derived from the sun direction and turbidity
+    perez: [[f32; 5]; 3],
+    zenith: glam::Vec3,
+    sun_color: glam::Vec3,
+}
+
+impl PhysicalSky {
+    pub fn new(sun_direction: glam::Vec3, turbidity: f32) -> Self {
+        let sun_direction = sun_direction.normalize();
+        let t = turbidity.clamp(1.7, 10.0);
+
+        let perez = [
+            // Y
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            // x
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            // y
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // zenith values are only defined for the sun above the horizon
+        let theta_s = sun_direction.y.clamp(0.0, 1.0).acos().min(PI / 2.0 - 0.01);
+        let theta_s2 = theta_s * theta_s;
+        let theta_s3 = theta_s2 * theta_s;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let zenith_x = t * t * (0.00166 * theta_s3 - 0.00375 * theta_s2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta_s3 + 0.06377 * theta_s2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta_s3 - 0.21196 * theta_s2 + 0.06052 * theta_s + 0.25886);
+
+        let zenith_y = t * t * (0.00275 * theta_s3 - 0.00610 * theta_s2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta_s3 + 0.08970 * theta_s2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta_s3 - 0.26756 * theta_s2 + 0.06670 * theta_s + 0.26688);
+
+        // the sun reddens as it gets closer to the horizon
+        let elevation = sun_direction.y.max(0.0);
+        let sun_color = glam::vec3(1.0, 0.45, 0.15)
+            .lerp(glam::vec3(1.0, 0.95, 0.9), elevation.powf(1.0 / 3.0))
+            * (elevation * 8.0).min(1.0);
+
+        let mut sky = Self {
+            sun_direction,
+            turbidity: t,
+            perez,
+            zenith: glam::vec3(zenith_luminance, zenith_x, zenith_y),
+            sun_color,
+        };
+
+        // normalise so that the zenith evaluates to the zenith values
+        for i in 0..3 {
+            sky.zenith[i] /= perez_function(&sky.perez[i], 0.0, theta_s);
+        }
+
+        sky
+    }
+
+    pub fn get_sun_direction(&self) -> glam::Vec3 {
+        self.sun_direction
+    }
+
+    pub fn get_turbidity(&self) -> f32 {
+        self.turbidity
+    }
+
+    fn radiance(&self, direction: glam::Vec3) -> glam::Vec3 {
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0);
+
+        let sun = if cos_gamma >= SUN_ANGULAR_RADIUS.cos() {
+            self.sun_color * SUN_RADIANCE
+        } else {
+            glam::Vec3::ZERO
+        };
+
+        // mirror the sky below the horizon and darken it to fake the ground
+        let ground = if direction.y < 0.0 { 0.3 } else { 1.0 };
+        let theta = direction.y.abs().max(0.01).acos();
+        let gamma = cos_gamma.acos();
+
+        let luminance = self.zenith.x * perez_function(&self.perez[0], theta, gamma);
+        let x = self.zenith.y * perez_function(&self.perez[1], theta, gamma);
+        let y = self.zenith.z * perez_function(&self.perez[2], theta, gamma);
+
+        sun + xyy_to_linear_srgb(x, y, luminance * SKY_LUMINANCE_SCALE) * ground
+    }
+
+    fn sample_sun(&self, u: glam::Vec2) -> glam::Vec3 {
+        sampling::uniform_cone(self.sun_direction, SUN_ANGULAR_RADIUS.cos(), u)
+    }
+
+    fn sun_pdf(&self, direction: glam::Vec3) -> f32 {
+        let cos_theta_max = SUN_ANGULAR_RADIUS.cos();
+
+        if direction.dot(self.sun_direction) >= cos_theta_max {
+            sampling::uniform_cone_pdf(cos_theta_max)
+        } else {
+            0.0
+        }
+    }
+}
+
+fn perez_function(coefficients: &[f32; 5], theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coefficients;
+    let cos_gamma = gamma.cos();
+
+    (1.0 + a * (b / theta.cos()).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+fn xyy_to_linear_srgb(x: f32, y: f32, luminance: f32) -> glam::Vec3 {
+    if y <= 0.0 {
+        return glam::Vec3::ZERO;
+    }
+
+    let xyz = glam::vec3(x * luminance / y, luminance, (1.0 - x - y) * luminance / y);
+
+    glam::vec3(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+    .max(glam::Vec3::ZERO)
+}
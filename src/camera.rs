@@ -1,8 +1,246 @@
+use std::f32::consts::PI;
+
 use glam::Vec4Swizzles;
 
+use crate::controls::Controls;
+use crate::ray::Ray;
+use crate::sampling;
 use crate::{KeyboardState, PointerState};
 
+// just short of straight up or down
+const MAX_PITCH: f32 = PI / 2.0 - 1e-3;
+
+// radians per second
+const ROLL_SPEED: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Lens {
+    // 0 is a pinhole camera with everything in focus
+    pub aperture_radius: f32,
+    pub focal_distance: f32,
+    // 0 is a circular aperture, otherwise the number of aperture blades
+    pub blades: u32,
+    pub blade_rotation: f32,
+}
+
+impl Default for Lens {
+    fn default() -> Self {
+        Self {
+            aperture_radius: 0.0,
+            focal_distance: 3.0,
+            blades: 0,
+            blade_rotation: 0.0,
+        }
+    }
+}
+
+// open and close times as fractions of the frame interval, objects only blur
+// while the shutter is open
+#[derive(Debug, Clone, Copy)]
+pub struct Shutter {
+    pub open: f32,
+    pub close: f32,
+}
+
+impl Default for Shutter {
+    fn default() -> Self {
+        Self {
+            open: 0.0,
+            close: 0.5,
+        }
+    }
+}
+
+impl Shutter {
+    pub fn sample_time(&self, u: f32) -> f32 {
+        self.open + (self.close - self.open) * u
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CameraSample {
+    // in pixels from the pixel centre
+    pub offset: glam::Vec2,
+    // in [0, 1)^2, mapped onto the aperture
+    pub lens: glam::Vec2,
+    // in [0, 1), mapped onto the shutter interval
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic { view_height: f32 },
+    // `fov` is the full angle covered by the image circle
+    Fisheye { fov: f32, mapping: FisheyeMapping },
+    Equirectangular,
+    Cylindrical { horizontal_fov: f32 },
+}
+
+impl Projection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Projection::Perspective => "Perspective",
+            Projection::Orthographic { .. } => "Orthographic",
+            Projection::Fisheye { .. } => "Fisheye",
+            Projection::Equirectangular => "Equirectangular",
+            Projection::Cylindrical { .. } => "Cylindrical panorama",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerMode {
+    // wasdqe and mouse look while the right mouse button is held
+    Fly,
+    // right drag turns around the pivot, which sits `orbit_distance` in front
+    // of the camera
+    Orbit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    Equidistant,
+    Equisolid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoMode {
+    Off,
+    // left eye on the left half
+    SideBySide,
+    // left eye on the top half
+    TopBottom,
+    // red from the left eye, green and blue from the right one
+    Anaglyph,
+}
+
+impl StereoMode {
+    pub const ALL: [StereoMode; 4] = [
+        StereoMode::Off,
+        StereoMode::SideBySide,
+        StereoMode::TopBottom,
+        StereoMode::Anaglyph,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StereoMode::Off => "Off",
+            StereoMode::SideBySide => "Side by side",
+            StereoMode::TopBottom => "Top / bottom",
+            StereoMode::Anaglyph => "Anaglyph (red / cyan)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    pub mode: StereoMode,
+    pub interocular_distance: f32,
+    // objects at this distance appear on the screen plane
+    pub convergence_distance: f32,
+}
+
+impl Default for Stereo {
+    fn default() -> Self {
+        Self {
+            mode: StereoMode::Off,
+            interocular_distance: 0.065,
+            convergence_distance: 3.0,
+        }
+    }
+}
+
+// camera body and lens in photographic units, scene units are metres
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalCamera {
+    pub sensor_width: f32,
+    pub sensor_height: f32,
+    pub focal_length: f32,
+    pub f_number: f32,
+    // in seconds
+    pub shutter_speed: f32,
+    pub iso: f32,
+}
+
+impl Default for PhysicalCamera {
+    // full frame 50mm, exposed for a dim interior
+    fn default() -> Self {
+        Self {
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            focal_length: 50.0,
+            f_number: 2.0,
+            shutter_speed: 1.0 / 30.0,
+            iso: 6400.0,
+        }
+    }
+}
+
+impl PhysicalCamera {
+    pub fn get_vfov(&self) -> f32 {
+        2.0 * (self.sensor_height / (2.0 * self.focal_length)).atan()
+    }
+
+    // focal length in millimetres for a vertical field of view
+    pub fn focal_length_for(&self, vfov: f32) -> f32 {
+        self.sensor_height / (2.0 * (vfov / 2.0).tan())
+    }
+
+    // the entrance pupil is the focal length over the f-number
+    pub fn get_aperture_radius(&self) -> f32 {
+        self.focal_length / self.f_number / 2.0 / 1000.0
+    }
+
+    // exposure value at ISO 100
+    pub fn get_ev100(&self) -> f32 {
+        (self.f_number * self.f_number / self.shutter_speed * 100.0 / self.iso).log2()
+    }
+
+    // maps scene luminance to sensor exposure, from the saturation based
+    // sensitivity with the usual 1.2 headroom before the sensor clips
+    pub fn get_exposure_scale(&self) -> f32 {
+        1.0 / (1.2 * 2.0_f32.powf(self.get_ev100()))
+    }
+}
+
+// everything needed to restore what the camera is looking at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewpoint {
+    pub position: glam::Vec3,
+    pub forward_direction: glam::Vec3,
+    // radians around the forward direction
+    pub roll: f32,
+    pub vfov: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub viewpoint: Viewpoint,
+}
+
+#[derive(Clone)]
 pub struct Camera {
+    pub lens: Lens,
+    pub shutter: Shutter,
+    pub controller_mode: ControllerMode,
+    pub controls: Controls,
+    // when set it drives the field of view, the aperture and the exposure
+    physical: Option<PhysicalCamera>,
+    // what the field of view and the aperture radius go back to when the
+    // physical camera is turned off
+    manual_vfov: f32,
+    manual_aperture_radius: f32,
+    stereo: Stereo,
+    projection_mode: Projection,
+
     vfov: f32,
     near_plane: f32,
     far_plane: f32,
@@ -13,20 +251,44 @@ pub struct Camera {
     inverse_view: glam::Mat4,
 
     position: glam::Vec3,
+    // relative to the up direction, yaw and pitch of 0 look down -z
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+    up_direction: glam::Vec3,
+    // follows the angles, updated with the view
     forward_direction: glam::Vec3,
+    // both modes keep it so switching between them doesn't move the view
+    orbit_distance: f32,
 
     viewport_height: u32,
     viewport_width: u32,
 
     last_mouse_position: glam::Vec2,
-
-    // cached
-    ray_directions: Vec<glam::Vec3>,
+    velocity: glam::Vec3,
+    // rotation the smoothing hasn't applied yet
+    pending_rotation: glam::Vec2,
+
+    // unnormalized direction through the bottom left corner of the image
+    // and the steps to the next pixel, perspective rays are built from them
+    pixel_origin: glam::Vec3,
+    pixel_delta_x: glam::Vec3,
+    pixel_delta_y: glam::Vec3,
 }
 
 impl Camera {
     pub fn new(vfov: f32, near_plane: f32, far_plane: f32) -> Self {
-        Self {
+        let mut camera = Self {
+            lens: Lens::default(),
+            shutter: Shutter::default(),
+            controller_mode: ControllerMode::Fly,
+            controls: Controls::default(),
+            physical: None,
+            manual_vfov: vfov,
+            manual_aperture_radius: Lens::default().aperture_radius,
+            stereo: Stereo::default(),
+            projection_mode: Projection::Perspective,
+
             vfov,
             near_plane,
             far_plane,
@@ -37,72 +299,241 @@ impl Camera {
             inverse_view: glam::Mat4::IDENTITY,
 
             position: glam::vec3(0.0, 0.0, 3.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            up_direction: glam::Vec3::Y,
             forward_direction: glam::vec3(0.0, 0.0, -1.0),
+            orbit_distance: 3.0,
 
-            ray_directions: vec![],
+            pixel_origin: glam::Vec3::ZERO,
+            pixel_delta_x: glam::Vec3::ZERO,
+            pixel_delta_y: glam::Vec3::ZERO,
 
             viewport_height: 0,
             viewport_width: 0,
 
             last_mouse_position: glam::Vec2::ZERO,
-        }
+            velocity: glam::Vec3::ZERO,
+            pending_rotation: glam::Vec2::ZERO,
+        };
+
+        camera.recalculate_view();
+        camera
     }
 
-    pub fn update(&mut self, dt: f32, pointer_state: PointerState, keyboard_state: KeyboardState) {
+    pub fn update(
+        &mut self,
+        dt: f32,
+        pointer_state: PointerState,
+        keyboard_state: KeyboardState,
+    ) -> bool {
         let Some(mouse_pos) = pointer_state.pos else {
-            return;
+            return false;
         };
 
-        if !pointer_state.secondary_down {
-            self.last_mouse_position = mouse_pos;
-            return;
+        let mouse_delta = mouse_pos - self.last_mouse_position;
+        self.last_mouse_position = mouse_pos;
+
+        let mut moved = false;
+
+        if pointer_state.middle_down {
+            moved |= self.pan(mouse_delta);
         }
 
-        let mouse_delta = (mouse_pos - self.last_mouse_position) * 0.002;
-        self.last_mouse_position = mouse_pos;
+        // while flying the wheel picks the move speed
+        let flying = pointer_state.secondary_down && self.controller_mode == ControllerMode::Fly;
+        if pointer_state.scroll != 0.0 {
+            if flying {
+                self.controls.move_speed = (self.controls.move_speed
+                    * (pointer_state.scroll * 0.002).exp())
+                .clamp(0.01, 1000.0);
+            } else {
+                moved |= self.dolly(pointer_state.scroll);
+            }
+        }
+
+        // yaw and pitch in radians, the mouse input is eased in over a few
+        // frames when smoothing is on
+        if pointer_state.secondary_down {
+            let invert = if self.controls.invert_y { -1.0 } else { 1.0 };
+            self.pending_rotation +=
+                mouse_delta * glam::vec2(1.0, invert) * self.controls.mouse_sensitivity;
+        }
+
+        let blend = self.controls.smoothing_factor(dt);
+        let rotation = if self.pending_rotation.length_squared() > 1e-10 {
+            self.pending_rotation * blend
+        } else {
+            self.pending_rotation
+        };
+        self.pending_rotation -= rotation;
+
+        moved |= match self.controller_mode {
+            ControllerMode::Fly => self.fly(dt, rotation, flying.then_some(keyboard_state), blend),
+            ControllerMode::Orbit => {
+                self.velocity = glam::Vec3::ZERO;
+                self.orbit(rotation)
+            }
+        };
+
+        if moved {
+            self.recalculate_view();
+            self.recalculate_image_plane();
+        }
+
+        moved
+    }
 
-        let up_direction = glam::Vec3::Y;
-        let speed = 5.0;
-        let right_direction = self.forward_direction.cross(up_direction);
+    fn fly(
+        &mut self,
+        dt: f32,
+        rotation: glam::Vec2,
+        keyboard_state: Option<KeyboardState>,
+        blend: f32,
+    ) -> bool {
+        let up_direction = self.up_direction;
+        let right_direction = self.get_orientation() * glam::Vec3::X;
 
         let mut moved = false;
-        let mut f = |c: bool, v: glam::Vec3| {
-            if c {
-                self.position += v * speed * dt;
+
+        // translation, the velocity eases towards the one asked by the keys
+        let mut target = glam::Vec3::ZERO;
+        if let Some(keyboard_state) = keyboard_state {
+            let mut f = |c: bool, v: glam::Vec3| {
+                if c {
+                    target += v;
+                }
+            };
+
+            f(keyboard_state.forward, self.forward_direction);
+            f(keyboard_state.backward, -self.forward_direction);
+            f(keyboard_state.left, -right_direction);
+            f(keyboard_state.right, right_direction);
+            f(keyboard_state.down, -up_direction);
+            f(keyboard_state.up, up_direction);
+
+            let roll = keyboard_state.roll_right as i32 - keyboard_state.roll_left as i32;
+            if roll != 0 {
+                self.roll = wrap_angle(self.roll + roll as f32 * ROLL_SPEED * dt);
                 moved = true;
             }
-        };
 
-        // translation
-        f(keyboard_state.w, self.forward_direction);
-        f(keyboard_state.s, -self.forward_direction);
-        f(keyboard_state.a, -right_direction);
-        f(keyboard_state.d, right_direction);
-        f(keyboard_state.q, -up_direction);
-        f(keyboard_state.e, up_direction);
+            let multiplier = match (keyboard_state.boost, keyboard_state.slow) {
+                (true, false) => 4.0,
+                (false, true) => 0.25,
+                _ => 1.0,
+            };
 
-        // rotation
-        if mouse_delta.x != 0.0 || mouse_delta.y != 0.0 {
-            let pitch_delta = mouse_delta.y * self.get_rotation_speed();
-            let yaw_delta = mouse_delta.x * self.get_rotation_speed();
+            target *= self.controls.move_speed * multiplier;
+        }
 
-            let q = (glam::Quat::from_axis_angle(right_direction, -pitch_delta)
-                * glam::Quat::from_axis_angle(glam::Vec3::Y, -yaw_delta))
-            .normalize();
+        self.velocity = self.velocity.lerp(target, blend);
+        if self.velocity.length_squared() < 1e-8 {
+            self.velocity = glam::Vec3::ZERO;
+        }
 
-            self.forward_direction = q * self.forward_direction;
+        if self.velocity != glam::Vec3::ZERO {
+            self.position += self.velocity * dt;
+            moved = true;
+        }
 
+        // rotation
+        if rotation != glam::Vec2::ZERO {
+            self.rotate(rotation);
             moved = true;
         }
 
         if moved {
-            self.recalculate_view();
-            self.recalculate_raydirections();
+            self.update_forward_direction();
+        }
+
+        moved
+    }
+
+    // yaw turns around the up direction, the pitch stops short of it so the
+    // view never flips over
+    fn rotate(&mut self, rotation: glam::Vec2) {
+        self.yaw = wrap_angle(self.yaw - rotation.x);
+        self.pitch = (self.pitch - rotation.y).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    // turns the camera around the pivot, which stays in the centre of the view
+    fn orbit(&mut self, rotation: glam::Vec2) -> bool {
+        if rotation == glam::Vec2::ZERO {
+            return false;
+        }
+
+        let pivot = self.get_pivot();
+
+        self.rotate(rotation);
+        self.update_forward_direction();
+        self.position = pivot - self.forward_direction * self.orbit_distance;
+
+        true
+    }
+
+    // moves the camera parallel to the image plane so that the pivot follows the cursor
+    fn pan(&mut self, mouse_delta: glam::Vec2) -> bool {
+        if mouse_delta.x == 0.0 && mouse_delta.y == 0.0 {
+            return false;
         }
+
+        let world_per_pixel = match self.projection_mode {
+            Projection::Orthographic { view_height } => view_height,
+            _ => 2.0 * self.orbit_distance * (self.vfov / 2.0).tan(),
+        } / self.get_eye_size().y.max(1) as f32;
+
+        let right = self.inverse_view.x_axis.xyz();
+        let up = self.inverse_view.y_axis.xyz();
+
+        self.position += (up * mouse_delta.y - right * mouse_delta.x) * world_per_pixel;
+
+        true
     }
 
-    pub const fn get_rotation_speed(&self) -> f32 {
-        0.3
+    // scrolling dollies towards the pivot in orbit mode and along the view
+    // direction in fly mode, an orthographic camera zooms instead
+    fn dolly(&mut self, scroll: f32) -> bool {
+        let factor = (-scroll * 0.002).exp();
+
+        if let Projection::Orthographic { view_height } = &mut self.projection_mode {
+            *view_height *= factor;
+            self.recalculate_projection();
+            return true;
+        }
+
+        match self.controller_mode {
+            ControllerMode::Fly => self.position += self.forward_direction * scroll * 0.01,
+            ControllerMode::Orbit => {
+                let pivot = self.get_pivot();
+                self.orbit_distance = (self.orbit_distance * factor).max(1e-3);
+                self.position = pivot - self.forward_direction * self.orbit_distance;
+            }
+        }
+
+        true
+    }
+
+    // points the camera at a bounding sphere from the current direction, close
+    // enough for it to fill the view
+    pub fn frame(&mut self, center: glam::Vec3, radius: f32) {
+        let eye_size = self.get_eye_size().as_vec2();
+        let aspect_ratio = eye_size.x / eye_size.y.max(1.0);
+        let half_fov = (self.vfov / 2.0)
+            .min(((self.vfov / 2.0).tan() * aspect_ratio).atan())
+            .max(1e-3);
+
+        self.orbit_distance = (radius / half_fov.sin()).max(1e-3);
+        self.position = center - self.forward_direction * self.orbit_distance;
+
+        if let Projection::Orthographic { view_height } = &mut self.projection_mode {
+            *view_height = 2.0 * radius / aspect_ratio.min(1.0);
+            self.recalculate_projection();
+        }
+
+        self.recalculate_view();
+        self.recalculate_image_plane();
     }
 
     pub fn resize(&mut self, new_height: u32, new_width: u32) {
@@ -114,76 +545,456 @@ impl Camera {
         self.viewport_width = new_width;
 
         self.recalculate_projection();
-        self.recalculate_raydirections();
+        self.recalculate_image_plane();
     }
 
+    // built from the orientation rather than a look at, which has no answer
+    // when looking along the up direction
     pub fn recalculate_view(&mut self) {
-        self.view = glam::Mat4::look_at_rh(
-            self.position,
-            self.position + self.forward_direction,
-            glam::Vec3::Y,
-        );
-        self.inverse_view = self.view.inverse();
+        self.update_forward_direction();
+
+        self.inverse_view =
+            glam::Mat4::from_rotation_translation(self.get_orientation(), self.position);
+        self.view = self.inverse_view.inverse();
     }
 
-    pub fn recalculate_raydirections(&mut self) {
-        self.ray_directions.clear();
-        self.ray_directions
-            .reserve(self.viewport_height as usize * self.viewport_width as usize);
+    pub fn get_orientation(&self) -> glam::Quat {
+        glam::Quat::from_rotation_arc(glam::Vec3::Y, self.up_direction)
+            * glam::Quat::from_rotation_y(self.yaw)
+            * glam::Quat::from_rotation_x(self.pitch)
+            * glam::Quat::from_rotation_z(self.roll)
+    }
 
-        for y in 0..self.viewport_height {
-            for x in 0..self.viewport_width {
-                let coord = glam::vec2(
-                    x as f32 / self.viewport_width as f32,
-                    y as f32 / self.viewport_height as f32,
-                ) * 2.0
-                    - 1.0;
+    fn update_forward_direction(&mut self) {
+        self.forward_direction = self.get_orientation() * glam::Vec3::NEG_Z;
+    }
 
-                let target = self.inverse_projection * glam::vec4(coord.x, coord.y, 1.0, 1.0);
+    // points the camera along `direction`, keeping the roll
+    fn look_along(&mut self, direction: glam::Vec3) {
+        let local = glam::Quat::from_rotation_arc(glam::Vec3::Y, self.up_direction).inverse()
+            * direction.normalize();
 
-                let t = (target.xyz() / target.w).normalize();
-                let ray_direction = (self.inverse_view * glam::vec4(t.x, t.y, t.z, 0.0)).xyz();
-                self.ray_directions.push(ray_direction);
-            }
-        }
+        self.pitch = local.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH);
+        self.yaw = (-local.x).atan2(-local.z);
+        self.update_forward_direction();
     }
 
+    // the directions through every point of the image end on the same plane,
+    // so any of them is a linear combination of three vectors
+    pub fn recalculate_image_plane(&mut self) {
+        self.pixel_origin = self.direction_through(0.0, 0.0);
+        self.pixel_delta_x = self.direction_through(1.0, 0.0) - self.pixel_origin;
+        self.pixel_delta_y = self.direction_through(0.0, 1.0) - self.pixel_origin;
+    }
+
+    fn direction_through(&self, x: f32, y: f32) -> glam::Vec3 {
+        let coord = glam::vec2(x, y) / self.get_eye_size().as_vec2() * 2.0 - 1.0;
+
+        let target = self.inverse_projection * glam::vec4(coord.x, coord.y, 1.0, 1.0);
+
+        let t = target.xyz() / target.w;
+        (self.inverse_view * glam::vec4(t.x, t.y, t.z, 0.0)).xyz()
+    }
+
+    // the wide angle projections can't be expressed as a matrix, they keep
+    // the perspective one around for anything that needs to project points
     pub fn recalculate_projection(&mut self) {
-        self.projection = glam::Mat4::perspective_rh(
-            self.vfov,
-            self.viewport_width as f32 / self.viewport_height as f32,
-            self.near_plane,
-            self.far_plane,
-        );
+        let eye_size = self.get_eye_size().as_vec2();
+        let aspect_ratio = eye_size.x / eye_size.y;
+
+        self.projection = match self.projection_mode {
+            Projection::Orthographic { view_height } => {
+                let half_height = view_height / 2.0;
+                let half_width = half_height * aspect_ratio;
+
+                glam::Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near_plane,
+                    self.far_plane,
+                )
+            }
+            _ => {
+                glam::Mat4::perspective_rh(self.vfov, aspect_ratio, self.near_plane, self.far_plane)
+            }
+        };
 
         self.inverse_projection = self.projection.inverse();
     }
 
-    pub fn get_projection(&self) -> &glam::Mat4 {
-        &self.projection
+    pub fn get_view(&self) -> &glam::Mat4 {
+        &self.view
     }
 
-    pub fn get_inverse_projection(&self) -> &glam::Mat4 {
-        &self.inverse_projection
+    pub fn get_direction(&self) -> &glam::Vec3 {
+        &self.forward_direction
     }
 
-    pub fn get_view(&self) -> &glam::Mat4 {
-        &self.view
+    pub fn get_physical(&self) -> Option<PhysicalCamera> {
+        self.physical
     }
 
-    pub fn get_inverse_view(&self) -> &glam::Mat4 {
-        &self.inverse_view
+    pub fn set_physical(&mut self, physical: Option<PhysicalCamera>) {
+        if self.physical.is_none() {
+            self.manual_vfov = self.vfov;
+            self.manual_aperture_radius = self.lens.aperture_radius;
+        }
+
+        self.physical = physical;
+
+        match physical {
+            Some(physical) => {
+                self.vfov = physical.get_vfov();
+                self.lens.aperture_radius = physical.get_aperture_radius();
+            }
+            None => {
+                self.vfov = self.manual_vfov;
+                self.lens.aperture_radius = self.manual_aperture_radius;
+            }
+        }
+
+        self.recalculate_projection();
+        self.recalculate_image_plane();
     }
 
-    pub fn get_position(&self) -> &glam::Vec3 {
-        &self.position
+    // linear factor the renderer applies before tone mapping
+    pub fn get_exposure_scale(&self) -> f32 {
+        self.physical
+            .map_or(1.0, |physical| physical.get_exposure_scale())
     }
 
-    pub fn get_direction(&self) -> &glam::Vec3 {
-        &self.forward_direction
+    pub fn get_up_direction(&self) -> glam::Vec3 {
+        self.up_direction
+    }
+
+    // keeps looking the same way, only the horizon changes
+    pub fn set_up_direction(&mut self, up_direction: glam::Vec3) {
+        let Some(up_direction) = up_direction.try_normalize() else {
+            return;
+        };
+
+        let forward_direction = self.forward_direction;
+        self.up_direction = up_direction;
+        self.look_along(forward_direction);
+
+        self.recalculate_view();
+        self.recalculate_image_plane();
+    }
+
+    pub fn get_roll(&self) -> f32 {
+        self.roll
+    }
+
+    pub fn set_roll(&mut self, roll: f32) {
+        self.roll = wrap_angle(roll);
+
+        self.recalculate_view();
+        self.recalculate_image_plane();
+    }
+
+    pub fn get_viewpoint(&self) -> Viewpoint {
+        Viewpoint {
+            position: self.position,
+            forward_direction: self.forward_direction,
+            roll: self.roll,
+            vfov: self.vfov,
+        }
     }
 
-    pub fn get_ray_directions(&self) -> &[glam::Vec3] {
-        &self.ray_directions
+    pub fn set_viewpoint(&mut self, viewpoint: Viewpoint) {
+        self.position = viewpoint.position;
+        self.roll = viewpoint.roll;
+        self.look_along(viewpoint.forward_direction);
+        self.vfov = viewpoint.vfov;
+
+        // zoom the lens rather than breaking the physical description, the
+        // entrance pupil follows the focal length
+        if let Some(physical) = &mut self.physical {
+            physical.focal_length = physical.focal_length_for(viewpoint.vfov);
+            self.lens.aperture_radius = physical.get_aperture_radius();
+        }
+
+        // don't let the inertia carry the camera away from the new view
+        self.velocity = glam::Vec3::ZERO;
+        self.pending_rotation = glam::Vec2::ZERO;
+
+        self.recalculate_view();
+        self.recalculate_projection();
+        self.recalculate_image_plane();
+    }
+
+    pub fn get_pivot(&self) -> glam::Vec3 {
+        self.position + self.forward_direction * self.orbit_distance
+    }
+
+    pub fn get_projection_mode(&self) -> Projection {
+        self.projection_mode
     }
+
+    pub fn set_projection_mode(&mut self, projection_mode: Projection) {
+        self.projection_mode = projection_mode;
+
+        self.recalculate_projection();
+        self.recalculate_image_plane();
+    }
+
+    // ray through `pixel`, pixel (0, 0) is the bottom left corner, `None`
+    // when the pixel is outside of the image circle of a fisheye
+    pub fn generate_ray(&self, pixel: glam::UVec2, sample: CameraSample) -> Option<Ray> {
+        let (pixel, eye) = self.split_stereo(pixel);
+        self.generate_eye_ray(pixel, sample, eye)
+    }
+
+    // `pixel` is relative to the image of the eye, `None` is the centre
+    // between the eyes
+    pub fn generate_eye_ray(
+        &self,
+        pixel: glam::UVec2,
+        sample: CameraSample,
+        eye: Option<Eye>,
+    ) -> Option<Ray> {
+        let time = self.shutter.sample_time(sample.time);
+        let point = pixel.as_vec2() + 0.5 + sample.offset;
+        let mut ray = self.pinhole_ray(point, time)?;
+
+        if let Some(eye) = eye {
+            ray = self.offset_to_eye(ray, eye);
+        }
+
+        let orthographic = matches!(self.projection_mode, Projection::Orthographic { .. });
+        if self.lens.aperture_radius <= 0.0 || orthographic {
+            return Some(ray);
+        }
+
+        // thin lens, every ray through the same pixel meets on the focal
+        // surface, a plane for perspective and a sphere for the wide angle
+        // projections
+        let focal_distance = match self.projection_mode {
+            Projection::Perspective => {
+                self.lens.focal_distance / ray.direction.dot(self.forward_direction)
+            }
+            _ => self.lens.focal_distance,
+        };
+
+        let focus_point = ray.origin + ray.direction * focal_distance;
+
+        let lens_point = if self.lens.blades >= 3 {
+            sampling::regular_polygon(sample.lens, self.lens.blades, self.lens.blade_rotation)
+        } else {
+            sampling::concentric_disk(sample.lens)
+        } * self.lens.aperture_radius;
+
+        let origin = ray.origin
+            + self.inverse_view.x_axis.xyz() * lens_point.x
+            + self.inverse_view.y_axis.xyz() * lens_point.y;
+
+        Some(Ray {
+            origin,
+            direction: (focus_point - origin).normalize(),
+            time,
+        })
+    }
+
+    // pinhole ray through a point of the viewport, in pixels from its top left corner
+    pub fn get_viewport_ray(&self, point: glam::Vec2) -> Option<Ray> {
+        let x = point.x;
+        let y = self.viewport_height as f32 - point.y;
+
+        if x < 0.0 || y < 0.0 || x >= self.viewport_width as f32 || y >= self.viewport_height as f32
+        {
+            return None;
+        }
+
+        let (pixel, _) = self.split_stereo(glam::uvec2(x as u32, y as u32));
+        let point = pixel.as_vec2() + glam::vec2(x.fract(), y.fract());
+
+        self.pinhole_ray(point, self.shutter.open)
+    }
+
+    // inverse of `get_viewport_ray`, `None` for points behind the camera and
+    // for the projections and stereo layouts a matrix can't describe
+    pub fn project_to_viewport(&self, point: glam::Vec3) -> Option<glam::Vec2> {
+        let clip = self.get_view_projection()? * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        let ndc = clip.xy() / clip.w;
+        Some(glam::vec2(
+            (ndc.x + 1.0) / 2.0 * self.viewport_width as f32,
+            (1.0 - ndc.y) / 2.0 * self.viewport_height as f32,
+        ))
+    }
+
+    // `None` when the image is not a single linear projection of the world,
+    // for the wide angle projections and the stereo modes with two images
+    pub fn get_view_projection(&self) -> Option<glam::Mat4> {
+        let linear = matches!(
+            self.projection_mode,
+            Projection::Perspective | Projection::Orthographic { .. }
+        );
+        let whole = matches!(self.stereo.mode, StereoMode::Off | StereoMode::Anaglyph);
+
+        (linear && whole).then(|| self.projection * self.view)
+    }
+
+    pub fn get_stereo(&self) -> Stereo {
+        self.stereo
+    }
+
+    pub fn set_stereo(&mut self, stereo: Stereo) {
+        self.stereo = stereo;
+
+        // side by side and top / bottom halve the image of each eye
+        self.recalculate_projection();
+        self.recalculate_image_plane();
+    }
+
+    // size of the image seen by one eye
+    pub fn get_eye_size(&self) -> glam::UVec2 {
+        let size = glam::uvec2(self.viewport_width, self.viewport_height);
+
+        match self.stereo.mode {
+            StereoMode::SideBySide => glam::uvec2(size.x / 2, size.y).max(glam::UVec2::ONE),
+            StereoMode::TopBottom => glam::uvec2(size.x, size.y / 2).max(glam::UVec2::ONE),
+            StereoMode::Off | StereoMode::Anaglyph => size,
+        }
+    }
+
+    // which eye a pixel of the viewport belongs to and where it is in the
+    // image of that eye, the anaglyph mode traces both eyes for every pixel
+    fn split_stereo(&self, pixel: glam::UVec2) -> (glam::UVec2, Option<Eye>) {
+        let eye_size = self.get_eye_size();
+
+        match self.stereo.mode {
+            StereoMode::SideBySide if pixel.x >= eye_size.x => {
+                (pixel - glam::uvec2(eye_size.x, 0), Some(Eye::Right))
+            }
+            StereoMode::SideBySide => (pixel, Some(Eye::Left)),
+            StereoMode::TopBottom if pixel.y >= eye_size.y => {
+                (pixel - glam::uvec2(0, eye_size.y), Some(Eye::Left))
+            }
+            StereoMode::TopBottom => (pixel, Some(Eye::Right)),
+            StereoMode::Off | StereoMode::Anaglyph => (pixel, None),
+        }
+    }
+
+    // parallel eyes with off axis frustums, the rays of both eyes through the
+    // same pixel meet on the convergence surface so it has no parallax
+    fn offset_to_eye(&self, ray: Ray, eye: Eye) -> Ray {
+        let side = match eye {
+            Eye::Left => -0.5,
+            Eye::Right => 0.5,
+        };
+
+        let offset = self.inverse_view.x_axis.xyz() * self.stereo.interocular_distance * side;
+
+        let convergence_distance = match self.projection_mode {
+            // parallel rays never converge, both eyes see the same image
+            Projection::Orthographic { .. } => {
+                return Ray {
+                    origin: ray.origin + offset,
+                    ..ray
+                };
+            }
+            Projection::Perspective => {
+                self.stereo.convergence_distance / ray.direction.dot(self.forward_direction)
+            }
+            _ => self.stereo.convergence_distance,
+        };
+
+        let target = ray.origin + ray.direction * convergence_distance;
+        let origin = ray.origin + offset;
+
+        Ray {
+            origin,
+            direction: (target - origin).normalize(),
+            time: ray.time,
+        }
+    }
+
+    // `point` is in pixels from the bottom left corner of the image
+    fn pinhole_ray(&self, point: glam::Vec2, time: f32) -> Option<Ray> {
+        if self.projection_mode == Projection::Perspective {
+            let direction =
+                self.pixel_origin + point.x * self.pixel_delta_x + point.y * self.pixel_delta_y;
+
+            return Some(Ray {
+                origin: self.position,
+                direction: direction.normalize(),
+                time,
+            });
+        }
+
+        let size = self.get_eye_size().as_vec2();
+        let coord = point / size * 2.0 - 1.0;
+        let aspect_ratio = size.x / size.y;
+
+        let right = self.inverse_view.x_axis.xyz();
+        let up = self.inverse_view.y_axis.xyz();
+
+        // direction in camera space, looking down -z
+        let local = match self.projection_mode {
+            Projection::Perspective => unreachable!(),
+            Projection::Orthographic { view_height } => {
+                let half_height = view_height / 2.0;
+                let offset = coord * glam::vec2(half_height * aspect_ratio, half_height);
+
+                return Some(Ray {
+                    origin: self.position + right * offset.x + up * offset.y,
+                    direction: self.forward_direction,
+                    time,
+                });
+            }
+            Projection::Fisheye { fov, mapping } => {
+                // the image circle touches the top and bottom of the viewport
+                let p = glam::vec2(coord.x * aspect_ratio, coord.y);
+                let r = p.length();
+
+                if r > 1.0 {
+                    return None;
+                }
+
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * fov / 2.0,
+                    FisheyeMapping::Equisolid => {
+                        2.0 * (r * (fov / 4.0).sin()).clamp(-1.0, 1.0).asin()
+                    }
+                };
+
+                let p = p.normalize_or_zero() * theta.sin();
+                glam::vec3(p.x, p.y, -theta.cos())
+            }
+            Projection::Equirectangular => {
+                let longitude = coord.x * PI;
+                let latitude = coord.y * PI / 2.0;
+
+                glam::vec3(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    -latitude.cos() * longitude.cos(),
+                )
+            }
+            Projection::Cylindrical { horizontal_fov } => {
+                // the unrolled cylinder keeps square pixels
+                let angle = coord.x * horizontal_fov / 2.0;
+                let height = coord.y * horizontal_fov / 2.0 / aspect_ratio;
+
+                glam::vec3(angle.sin(), height, -angle.cos()).normalize()
+            }
+        };
+
+        Some(Ray {
+            origin: self.position,
+            direction: (self.inverse_view * local.extend(0.0)).xyz(),
+            time,
+        })
+    }
+}
+
+// into [-pi, pi)
+pub fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
 }
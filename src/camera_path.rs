@@ -0,0 +1,153 @@
+use crate::camera::{self, Viewpoint};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    // in seconds from the start of the path
+    pub time: f32,
+    pub viewpoint: Viewpoint,
+}
+
+// keyframes are kept sorted by time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    // circles `center` once at `distance`, `height` above it
+    pub fn turntable(
+        center: glam::Vec3,
+        distance: f32,
+        height: f32,
+        vfov: f32,
+        duration: f32,
+    ) -> Self {
+        let mut path = Self::default();
+        let steps = 8;
+
+        for i in 0..=steps {
+            let angle = i as f32 / steps as f32 * std::f32::consts::TAU;
+            let position =
+                center + glam::vec3(angle.sin() * distance, height, angle.cos() * distance);
+
+            path.insert(Keyframe {
+                time: i as f32 / steps as f32 * duration,
+                viewpoint: Viewpoint {
+                    position,
+                    forward_direction: (center - position).normalize(),
+                    roll: 0.0,
+                    vfov,
+                },
+            });
+        }
+
+        path
+    }
+
+    pub fn get_keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.keyframes.remove(index);
+    }
+
+    pub fn set_time(&mut self, index: usize, time: f32) {
+        let mut keyframe = self.keyframes.remove(index);
+        keyframe.time = time;
+        self.insert(keyframe);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    // catmull-rom through the positions and slerp between the orientations,
+    // the path holds still before the first and after the last keyframe,
+    // `up` is the up direction of the camera the path drives
+    pub fn evaluate(&self, time: f32, up: glam::Vec3) -> Option<Viewpoint> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        if time <= first.time {
+            return Some(first.viewpoint);
+        }
+
+        if time >= last.time {
+            return Some(last.viewpoint);
+        }
+
+        let i = self.keyframes.partition_point(|k| k.time <= time) - 1;
+        let (k1, k2) = (&self.keyframes[i], &self.keyframes[i + 1]);
+
+        // the end points are repeated to get tangents for the outer segments
+        let k0 = &self.keyframes[i.saturating_sub(1)];
+        let k3 = &self.keyframes[(i + 2).min(self.keyframes.len() - 1)];
+
+        let span = k2.time - k1.time;
+        let t = if span > 0.0 {
+            (time - k1.time) / span
+        } else {
+            0.0
+        };
+
+        let position = catmull_rom(
+            k0.viewpoint.position,
+            k1.viewpoint.position,
+            k2.viewpoint.position,
+            k3.viewpoint.position,
+            t,
+        );
+
+        let orientation = orientation(k1.viewpoint.forward_direction, up)
+            .slerp(orientation(k2.viewpoint.forward_direction, up), t);
+
+        // the short way around
+        let roll =
+            k1.viewpoint.roll + camera::wrap_angle(k2.viewpoint.roll - k1.viewpoint.roll) * t;
+
+        Some(Viewpoint {
+            position,
+            forward_direction: orientation * glam::Vec3::NEG_Z,
+            roll: camera::wrap_angle(roll),
+            vfov: k1.viewpoint.vfov + (k2.viewpoint.vfov - k1.viewpoint.vfov) * t,
+        })
+    }
+}
+
+fn catmull_rom(
+    p0: glam::Vec3,
+    p1: glam::Vec3,
+    p2: glam::Vec3,
+    p3: glam::Vec3,
+    t: f32,
+) -> glam::Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+// rotation taking -z to `forward` with the camera kept upright
+fn orientation(forward: glam::Vec3, up: glam::Vec3) -> glam::Quat {
+    let forward = forward.normalize();
+    let right = forward
+        .cross(up)
+        .try_normalize()
+        .unwrap_or_else(|| forward.any_orthonormal_vector());
+    let up = right.cross(forward);
+
+    glam::Quat::from_mat3(&glam::Mat3::from_cols(right, up, -forward))
+}
@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+// `controls.cfg` in the config directory of the user, the working directory
+// when there is none
+pub fn config_path() -> PathBuf {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    let directory = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("APPDATA").map(PathBuf::from))
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")));
+
+    match directory {
+        Some(directory) => directory.join("realtime-raytracing").join("controls.cfg"),
+        None => PathBuf::from("controls.cfg"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Down,
+    Up,
+    RollLeft,
+    RollRight,
+    Frame,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Forward,
+        Action::Backward,
+        Action::Left,
+        Action::Right,
+        Action::Down,
+        Action::Up,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::Frame,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Forward => "Forward",
+            Action::Backward => "Backward",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Down => "Down",
+            Action::Up => "Up",
+            Action::RollLeft => "Roll left",
+            Action::RollRight => "Roll right",
+            Action::Frame => "Frame selection",
+        }
+    }
+
+    // key of the binding in the config file
+    fn config_name(self) -> &'static str {
+        match self {
+            Action::Forward => "forward",
+            Action::Backward => "backward",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::RollLeft => "roll_left",
+            Action::RollRight => "roll_right",
+            Action::Frame => "frame_scene",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyBindings {
+    keys: [egui::Key; Action::ALL.len()],
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: [
+                egui::Key::W,
+                egui::Key::S,
+                egui::Key::A,
+                egui::Key::D,
+                egui::Key::Q,
+                egui::Key::E,
+                egui::Key::R,
+                egui::Key::T,
+                egui::Key::F,
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: Action) -> egui::Key {
+        self.keys[action as usize]
+    }
+
+    pub fn set(&mut self, action: Action, key: egui::Key) {
+        self.keys[action as usize] = key;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Controls {
+    // units per second
+    pub move_speed: f32,
+    // radians per pixel
+    pub mouse_sensitivity: f32,
+    // time constant of the inertia in seconds, 0 moves the camera immediately
+    pub smoothing: f32,
+    pub invert_y: bool,
+    pub bindings: KeyBindings,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            move_speed: 5.0,
+            mouse_sensitivity: 0.0006,
+            smoothing: 0.0,
+            invert_y: false,
+            bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Controls {
+    // fraction of the remaining motion to apply this frame, the same amount of
+    // time settles the camera the same amount whatever the frame rate
+    pub fn smoothing_factor(&self, dt: f32) -> f32 {
+        if self.smoothing > 0.0 {
+            1.0 - (-dt / self.smoothing).exp()
+        } else {
+            1.0
+        }
+    }
+
+    // `key = value` lines, anything missing keeps its default
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut controls = Self::default();
+
+        let invalid = |line: &str| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid config line: {line}"),
+            )
+        };
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                "move_speed" => controls.move_speed = value.parse().map_err(|_| invalid(line))?,
+                "mouse_sensitivity" => {
+                    controls.mouse_sensitivity = value.parse().map_err(|_| invalid(line))?
+                }
+                "smoothing" => controls.smoothing = value.parse().map_err(|_| invalid(line))?,
+                "invert_y" => controls.invert_y = value.parse().map_err(|_| invalid(line))?,
+                _ => {
+                    let Some(action) = key
+                        .strip_prefix("key.")
+                        .and_then(|name| Action::ALL.into_iter().find(|a| a.config_name() == name))
+                    else {
+                        continue;
+                    };
+
+                    let key = egui::Key::from_name(value).ok_or_else(|| invalid(line))?;
+                    controls.bindings.set(action, key);
+                }
+            }
+        }
+
+        Ok(controls)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        let mut text = format!(
+            "move_speed = {}\nmouse_sensitivity = {}\nsmoothing = {}\ninvert_y = {}\n",
+            self.move_speed, self.mouse_sensitivity, self.smoothing, self.invert_y
+        );
+
+        for action in Action::ALL {
+            text += &format!(
+                "key.{} = {}\n",
+                action.config_name(),
+                self.bindings.get(action).name()
+            );
+        }
+
+        std::fs::write(path, text)
+    }
+}
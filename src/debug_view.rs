@@ -0,0 +1,164 @@
+use std::f32::consts::PI;
+
+use crate::ray::Ray;
+use crate::tonemap;
+use crate::world::World;
+
+// distance at which the depth view has faded to 1/e
+const DEPTH_FALLOFF: f32 = 10.0;
+
+// intersection tests per sample at the middle of the cost heatmap
+const COST_SCALE: f32 = 8.0;
+
+// shows what the primary rays hit instead of the lit image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    Beauty,
+    Normals,
+    Albedo,
+    Depth,
+    Uv,
+    ObjectId,
+    MaterialId,
+    // every ray tests every sphere, there is no acceleration structure to
+    // traverse, so this is the number of rays of a path times the object count
+    Cost,
+    // of the luminance of the beauty samples, relative to their mean so
+    // bright and dark areas compare
+    Variance,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 9] = [
+        DebugView::Beauty,
+        DebugView::Normals,
+        DebugView::Albedo,
+        DebugView::Depth,
+        DebugView::Uv,
+        DebugView::ObjectId,
+        DebugView::MaterialId,
+        DebugView::Cost,
+        DebugView::Variance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Beauty => "Beauty",
+            DebugView::Normals => "Normals",
+            DebugView::Albedo => "Albedo",
+            DebugView::Depth => "Depth",
+            DebugView::Uv => "UV",
+            DebugView::ObjectId => "Object ID",
+            DebugView::MaterialId => "Material ID",
+            DebugView::Cost => "Intersection cost",
+            DebugView::Variance => "Variance",
+        }
+    }
+
+    // for the command line
+    pub fn config_name(self) -> &'static str {
+        match self {
+            DebugView::Beauty => "beauty",
+            DebugView::Normals => "normals",
+            DebugView::Albedo => "albedo",
+            DebugView::Depth => "depth",
+            DebugView::Uv => "uv",
+            DebugView::ObjectId => "object-id",
+            DebugView::MaterialId => "material-id",
+            DebugView::Cost => "cost",
+            DebugView::Variance => "variance",
+        }
+    }
+
+    pub fn from_config_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|view| view.config_name() == name)
+    }
+
+    // the views that look at the full paths rather than the first hit
+    pub fn traces_paths(self) -> bool {
+        matches!(
+            self,
+            DebugView::Beauty | DebugView::Cost | DebugView::Variance
+        )
+    }
+
+    // the value accumulated for one primary ray, `forward` is the view
+    // direction the depth is measured along
+    pub fn trace(self, ray: &Ray, world: &World, forward: glam::Vec3) -> glam::Vec3 {
+        let Some(hit) = world.intersect(ray, 0.0, f32::INFINITY) else {
+            return glam::Vec3::ZERO;
+        };
+
+        let sphere = &world.objects[hit.object_index];
+
+        match self {
+            DebugView::Normals => hit.normal,
+            DebugView::Albedo => world.get_material(hit.object_index).albedo,
+            DebugView::Depth => glam::Vec3::splat((hit.position - ray.origin).dot(forward)),
+            DebugView::Uv => {
+                // latitude and longitude around the local y axis
+                let local = sphere.rotation.inverse() * hit.normal;
+                let u = local.x.atan2(local.z) / (2.0 * PI) + 0.5;
+                let v = local.y.clamp(-1.0, 1.0).acos() / PI;
+
+                glam::vec3(u, 1.0 - v, 0.0)
+            }
+            DebugView::ObjectId => false_color(hit.object_index),
+            DebugView::MaterialId => false_color(sphere.material_index),
+            DebugView::Beauty | DebugView::Cost | DebugView::Variance => unreachable!(),
+        }
+    }
+
+    // display referred srgb from the weighted average of the accumulated
+    // values, the exposure and the tone mapper only apply to the beauty image
+    pub fn display(self, value: glam::Vec3) -> glam::Vec3 {
+        match self {
+            DebugView::Beauty => unreachable!(),
+            DebugView::Normals => value * 0.5 + 0.5,
+            DebugView::Albedo => tonemap::linear_to_srgb(value),
+            // near is bright, the background stays black
+            DebugView::Depth if value.x <= 0.0 => glam::Vec3::ZERO,
+            DebugView::Depth => glam::Vec3::splat((-value.x / DEPTH_FALLOFF).exp()),
+            DebugView::Uv | DebugView::ObjectId | DebugView::MaterialId => value,
+            DebugView::Cost => heatmap(value.x / (value.x + COST_SCALE)),
+            // the mean luminance and the mean of its square
+            DebugView::Variance => {
+                let variance = (value.y - value.x * value.x).max(0.0);
+                let relative = variance.sqrt() / value.x.max(1e-4);
+
+                heatmap(relative / (relative + 1.0))
+            }
+        }
+    }
+}
+
+// a colour that tells neighbouring indices apart
+fn false_color(index: usize) -> glam::Vec3 {
+    // golden ratio steps spread the hues evenly
+    let hue = (index as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+
+    let rgb = match hue as u32 {
+        0 => glam::vec3(1.0, x, 0.0),
+        1 => glam::vec3(x, 1.0, 0.0),
+        2 => glam::vec3(0.0, 1.0, x),
+        3 => glam::vec3(0.0, x, 1.0),
+        4 => glam::vec3(x, 0.0, 1.0),
+        _ => glam::vec3(1.0, 0.0, x),
+    };
+
+    rgb * 0.7 + 0.25
+}
+
+// blue through green to red for `t` in [0, 1]
+fn heatmap(t: f32) -> glam::Vec3 {
+    let t = t.clamp(0.0, 1.0);
+
+    glam::vec3(
+        (t * 2.0 - 0.5).clamp(0.0, 1.0),
+        (1.0 - (t * 2.0 - 1.0).abs() * 1.5).clamp(0.0, 1.0),
+        (1.5 - t * 2.0).clamp(0.0, 1.0),
+    )
+}
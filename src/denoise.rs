@@ -0,0 +1,172 @@
+use crate::aov::AovSample;
+use crate::tonemap;
+
+// every pass doubles the distance between the taps, so the filter reaches
+// 2 * (2^PASSES - 1) pixels
+const PASSES: u32 = 5;
+
+// b3 spline, from the centre out
+const KERNEL: [f32; 3] = [3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// exponent of the cosine between the normals
+const NORMAL_PHI: i32 = 64;
+
+// relative depth difference per pixel of distance
+const DEPTH_PHI: f32 = 0.05;
+
+const ALBEDO_PHI: f32 = 0.1;
+
+// relative luminance difference at strength 1 for a single sample per pixel
+const COLOR_PHI: f32 = 4.0;
+
+// edge-avoiding à-trous wavelet filter (Dammertz et al. 2010) of a linear
+// image, rows in any order, guided by the first hits in `guides`, which are
+// resolved aovs, `samples` is the number of samples each pixel averages
+pub fn denoise(
+    color: &[glam::Vec3],
+    guides: &[AovSample],
+    samples: &[f32],
+    width: usize,
+    height: usize,
+    strength: f32,
+) -> Vec<glam::Vec3> {
+    let mut color = color.to_vec();
+
+    if strength <= 0.0
+        || color.len() != width * height
+        || guides.len() != color.len()
+        || samples.len() != color.len()
+    {
+        return color;
+    }
+
+    let mut color_phi = strength * COLOR_PHI;
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = height.div_ceil(threads).max(1) * width;
+
+    for pass in 0..PASSES {
+        let input = color.clone();
+        let pass = Pass {
+            color: &input,
+            guides,
+            samples,
+            width,
+            height,
+            step: 1 << pass,
+            color_phi,
+        };
+
+        std::thread::scope(|s| {
+            for (chunk_index, output) in color.chunks_mut(chunk_size).enumerate() {
+                let pass = &pass;
+
+                s.spawn(move || {
+                    for (i, pixel) in output.iter_mut().enumerate() {
+                        let index = chunk_index * chunk_size + i;
+                        *pixel = pass.filter(index % width, index / width);
+                    }
+                });
+            }
+        });
+
+        // the later passes see less noise
+        color_phi *= 0.5;
+    }
+
+    color
+}
+
+struct Pass<'a> {
+    color: &'a [glam::Vec3],
+    guides: &'a [AovSample],
+    samples: &'a [f32],
+    width: usize,
+    height: usize,
+    // in pixels between the taps
+    step: i32,
+    // for a single sample per pixel
+    color_phi: f32,
+}
+
+impl Pass<'_> {
+    fn filter(&self, x: usize, y: usize) -> glam::Vec3 {
+        let Pass {
+            color,
+            guides,
+            samples,
+            width,
+            height,
+            step,
+            color_phi,
+        } = *self;
+
+        let index = x + y * width;
+        let centre = &guides[index];
+
+        // the background is left alone, it has nothing to guide the filter
+        if centre.depth <= 0.0 {
+            return color[index];
+        }
+
+        // the noise of an average falls with the square root of the sample
+        // count, which differs between the pixels after a reprojection
+        let color_phi = color_phi / samples[index].max(1.0).sqrt();
+
+        let normal = centre.normal.normalize_or_zero();
+        let luminance = tonemap::luminance(color[index]);
+
+        let mut sum = glam::Vec3::ZERO;
+        let mut weight_sum = 0.0;
+
+        for dy in -2..=2_i32 {
+            for dx in -2..=2_i32 {
+                let (qx, qy) = (x as i32 + dx * step, y as i32 + dy * step);
+
+                if qx < 0 || qy < 0 || qx >= width as i32 || qy >= height as i32 {
+                    continue;
+                }
+
+                let q = qx as usize + qy as usize * width;
+                let guide = &guides[q];
+
+                if guide.depth <= 0.0 {
+                    continue;
+                }
+
+                let distance = ((dx * dx + dy * dy) as f32).sqrt() * step as f32;
+
+                let normal_weight = normal
+                    .dot(guide.normal.normalize_or_zero())
+                    .max(0.0)
+                    .powi(NORMAL_PHI);
+                let depth_weight = (-(centre.depth - guide.depth).abs()
+                    / (DEPTH_PHI * centre.depth * distance.max(1.0)))
+                .exp();
+                let albedo_weight = (-(centre.albedo - guide.albedo).length_squared()
+                    / (ALBEDO_PHI * ALBEDO_PHI))
+                    .exp();
+
+                let other = tonemap::luminance(color[q]);
+                let color_weight =
+                    (-(luminance - other).abs() / (color_phi * luminance.max(other) + 1e-4)).exp();
+
+                let weight = KERNEL[dx.unsigned_abs() as usize]
+                    * KERNEL[dy.unsigned_abs() as usize]
+                    * normal_weight
+                    * depth_weight
+                    * albedo_weight
+                    * color_weight;
+
+                sum += color[q] * weight;
+                weight_sum += weight;
+            }
+        }
+
+        if weight_sum > 0.0 {
+            sum / weight_sum
+        } else {
+            color[index]
+        }
+    }
+}
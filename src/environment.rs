@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+use std::path::Path;
+
+use crate::image_io::{self, FloatImage};
+use crate::tonemap;
+
+// equirectangular environment map, importance sampled through a piecewise
+// constant 2d distribution over its pixels weighted by sin(theta)
+pub struct EnvironmentMap {
+    image: FloatImage,
+
+    // one cdf with `width + 1` entries per row, and the marginal cdf over the
+    // rows with `height + 1` entries
+    conditional_cdf: Vec<f32>,
+    marginal_cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl EnvironmentMap {
+    pub fn from_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let image = image_io::read_float_image(path)?;
+
+        if image.width == 0 || image.height == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "empty environment map",
+            ));
+        }
+
+        Ok(Self::new(image))
+    }
+
+    pub fn new(image: FloatImage) -> Self {
+        let (width, height) = (image.width, image.height);
+
+        let mut conditional_cdf = Vec::with_capacity((width + 1) * height);
+        let mut row_integrals = Vec::with_capacity(height);
+
+        for y in 0..height {
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            let row = &image.pixels[y * width..(y + 1) * width];
+
+            let start = conditional_cdf.len();
+            conditional_cdf.push(0.0);
+
+            let mut sum = 0.0;
+            for pixel in row {
+                sum += tonemap::luminance(*pixel) * sin_theta / width as f32;
+                conditional_cdf.push(sum);
+            }
+
+            normalize_cdf(&mut conditional_cdf[start..], sum);
+            row_integrals.push(sum);
+        }
+
+        let mut marginal_cdf = Vec::with_capacity(height + 1);
+        marginal_cdf.push(0.0);
+
+        let mut integral = 0.0;
+        for row_integral in &row_integrals {
+            integral += row_integral / height as f32;
+            marginal_cdf.push(integral);
+        }
+
+        normalize_cdf(&mut marginal_cdf, integral);
+
+        Self {
+            image,
+            conditional_cdf,
+            marginal_cdf,
+            integral,
+        }
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.integral <= 0.0
+    }
+
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.image.width, self.image.height)
+    }
+
+    pub fn radiance(&self, direction: glam::Vec3) -> glam::Vec3 {
+        let (x, y) = self.to_pixel(direction_to_uv(direction));
+        self.image.pixels[x + y * self.image.width]
+    }
+
+    pub fn sample(&self, u: glam::Vec2) -> glam::Vec3 {
+        let width = self.image.width;
+
+        let (y, v) = sample_cdf(&self.marginal_cdf, u.y);
+        let row = &self.conditional_cdf[y * (width + 1)..(y + 1) * (width + 1)];
+        let (_, u) = sample_cdf(row, u.x);
+
+        let uv = glam::vec2(u, v).clamp(glam::Vec2::ZERO, glam::Vec2::splat(1.0 - f32::EPSILON));
+
+        uv_to_direction(uv)
+    }
+
+    pub fn pdf(&self, direction: glam::Vec3) -> f32 {
+        if self.is_black() {
+            return 0.0;
+        }
+
+        let uv = direction_to_uv(direction);
+        let sin_theta = (PI * uv.y).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let (x, y) = self.to_pixel(uv);
+        let (width, height) = self.get_size();
+
+        let sin_theta_row = (PI * (y as f32 + 0.5) / height as f32).sin();
+        let value = tonemap::luminance(self.image.pixels[x + y * width]) * sin_theta_row;
+
+        // pdf over the unit square, converted to solid angle
+        (value / self.integral) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn to_pixel(&self, uv: glam::Vec2) -> (usize, usize) {
+        let (width, height) = self.get_size();
+
+        (
+            ((uv.x * width as f32) as usize).min(width - 1),
+            ((uv.y * height as f32) as usize).min(height - 1),
+        )
+    }
+}
+
+impl std::fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.image.width)
+            .field("height", &self.image.height)
+            .finish_non_exhaustive()
+    }
+}
+
+// u follows the azimuth with -z at the centre of the image, v goes from the
+// zenith at the top to the nadir at the bottom
+fn direction_to_uv(direction: glam::Vec3) -> glam::Vec2 {
+    glam::vec2(
+        0.5 + direction.x.atan2(-direction.z) / (2.0 * PI),
+        direction.y.clamp(-1.0, 1.0).acos() / PI,
+    )
+}
+
+fn uv_to_direction(uv: glam::Vec2) -> glam::Vec3 {
+    let phi = (uv.x - 0.5) * 2.0 * PI;
+    let theta = uv.y * PI;
+
+    glam::vec3(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+fn normalize_cdf(cdf: &mut [f32], sum: f32) {
+    let n = (cdf.len() - 1) as f32;
+
+    for (i, value) in cdf.iter_mut().enumerate() {
+        // a black row is sampled uniformly
+        *value = if sum > 0.0 {
+            *value / sum
+        } else {
+            i as f32 / n
+        };
+    }
+}
+
+// returns the sampled segment and the continuous position inside of the
+// distribution, remapped to [0, 1)
+fn sample_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+    let n = cdf.len() - 1;
+    let i = cdf.partition_point(|&c| c <= u).clamp(1, n) - 1;
+
+    let width = cdf[i + 1] - cdf[i];
+    let offset = if width > 0.0 {
+        (u - cdf[i]) / width
+    } else {
+        0.5
+    };
+
+    (i, (i as f32 + offset) / n as f32)
+}
@@ -0,0 +1,156 @@
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    BlackmanHarris,
+}
+
+// resolution of the tabulated filter used for importance sampling
+const TABLE_SIZE: usize = 64;
+
+impl Filter {
+    pub const ALL: [Filter; 5] = [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian,
+        Filter::Mitchell,
+        Filter::BlackmanHarris,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::Box => "Box",
+            Filter::Tent => "Tent",
+            Filter::Gaussian => "Gaussian",
+            Filter::Mitchell => "Mitchell-Netravali",
+            Filter::BlackmanHarris => "Blackman-Harris",
+        }
+    }
+
+    // in pixels
+    pub fn radius(self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+            Filter::BlackmanHarris => 2.0,
+        }
+    }
+
+    // all filters are separable, this is the 1d profile
+    pub fn evaluate(self, x: f32) -> f32 {
+        let r = self.radius();
+        let x = x.abs();
+
+        if x > r {
+            return 0.0;
+        }
+
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => r - x,
+            Filter::Gaussian => {
+                let sigma = 0.5_f32;
+                let g = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+                (g(x) - g(r)).max(0.0)
+            }
+            Filter::Mitchell => {
+                // B = C = 1/3, the kernel is defined over [-2, 2]
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = x * 2.0 / r;
+
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                }
+            }
+            Filter::BlackmanHarris => {
+                let t = 2.0 * PI * (x / (2.0 * r) + 0.5);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            }
+        }
+    }
+}
+
+// filter importance sampling: offsets are drawn proportionally to |f| and
+// carry the sign of f as their weight, scaled by ∫|f| / ∫f so the weights
+// average to one and the weighted samples are averaged by their count, which
+// keeps negative lobes intact
+pub struct FilterSampler {
+    filter: Filter,
+    cdf: Vec<f32>,
+    // ∫|f| / ∫f of the 2d filter
+    scale: f32,
+}
+
+impl FilterSampler {
+    pub fn new(filter: Filter) -> Self {
+        let r = filter.radius();
+
+        let mut cdf = Vec::with_capacity(TABLE_SIZE + 1);
+        cdf.push(0.0);
+
+        let mut sum = 0.0;
+        let mut signed_sum = 0.0;
+        for i in 0..TABLE_SIZE {
+            let x = -r + 2.0 * r * (i as f32 + 0.5) / TABLE_SIZE as f32;
+            sum += filter.evaluate(x).abs();
+            signed_sum += filter.evaluate(x);
+            cdf.push(sum);
+        }
+
+        cdf.iter_mut().for_each(|c| *c /= sum);
+
+        // the filter is separable, so are its integrals
+        let scale = (sum / signed_sum).powi(2);
+
+        Self { filter, cdf, scale }
+    }
+
+    pub fn get_filter(&self) -> Filter {
+        self.filter
+    }
+
+    // maps a sample in [0, 1)^2 to an offset from the pixel centre, in pixels,
+    // and the weight of the sample, which is one on average
+    pub fn sample(&self, u: glam::Vec2) -> (glam::Vec2, f32) {
+        let (x, wx) = self.sample_1d(u.x);
+        let (y, wy) = self.sample_1d(u.y);
+
+        (glam::vec2(x, y), wx * wy * self.scale)
+    }
+
+    fn sample_1d(&self, u: f32) -> (f32, f32) {
+        let r = self.filter.radius();
+
+        let i = self.cdf.partition_point(|&c| c <= u).clamp(1, TABLE_SIZE) - 1;
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let t = if width > 0.0 {
+            (u - self.cdf[i]) / width
+        } else {
+            0.5
+        };
+
+        let x = -r + 2.0 * r * (i as f32 + t) / TABLE_SIZE as f32;
+        let weight = if self.filter.evaluate(x) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        (x, weight)
+    }
+}
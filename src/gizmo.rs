@@ -0,0 +1,557 @@
+use eframe::egui;
+
+use crate::camera::Camera;
+use crate::ray::Ray;
+use crate::world::Sphere;
+
+// length of the axes in points, the gizmo keeps its size on screen
+const SIZE: f32 = 90.0;
+// how close the pointer has to be to grab a handle
+const PICK_DISTANCE: f32 = 6.0;
+const RING_SEGMENTS: usize = 64;
+// the smallest radius the scale handles go down to
+const MIN_RADIUS: f32 = 0.01;
+
+const AXIS_COLORS: [egui::Color32; 3] = [
+    egui::Color32::from_rgb(230, 70, 70),
+    egui::Color32::from_rgb(90, 200, 90),
+    egui::Color32::from_rgb(80, 120, 240),
+];
+const CENTRE_COLOR: egui::Color32 = egui::Color32::from_rgb(220, 220, 220);
+const ACTIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 220, 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub const ALL: [GizmoMode; 3] = [GizmoMode::Translate, GizmoMode::Rotate, GizmoMode::Scale];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GizmoMode::Translate => "Translate",
+            GizmoMode::Rotate => "Rotate",
+            GizmoMode::Scale => "Scale",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoSpace {
+    World,
+    // along the rotation of the object
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handle {
+    Axis(usize),
+    // the plane perpendicular to the axis
+    Plane(usize),
+    Ring(usize),
+    // uniform scale, dragging up grows the object
+    Centre,
+}
+
+// where the gizmo is, in world space
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    centre: glam::Vec3,
+    axes: [glam::Vec3; 3],
+    // world space length of the axes
+    length: f32,
+}
+
+impl Frame {
+    fn new(camera: &Camera, sphere: &Sphere, space: GizmoSpace) -> Option<Self> {
+        let centre = sphere.position;
+
+        // how many pixels one unit covers at the centre
+        let up = camera.get_orientation() * glam::Vec3::Y;
+        let scale = camera
+            .project_to_viewport(centre + up)?
+            .distance(camera.project_to_viewport(centre)?);
+
+        if scale <= f32::EPSILON {
+            return None;
+        }
+
+        let rotation = match space {
+            GizmoSpace::World => glam::Quat::IDENTITY,
+            GizmoSpace::Local => sphere.rotation,
+        };
+
+        Some(Self {
+            centre,
+            axes: [
+                rotation * glam::Vec3::X,
+                rotation * glam::Vec3::Y,
+                rotation * glam::Vec3::Z,
+            ],
+            length: SIZE / scale,
+        })
+    }
+
+    // the other two axes, in order
+    fn plane_axes(&self, axis: usize) -> (glam::Vec3, glam::Vec3) {
+        (self.axes[(axis + 1) % 3], self.axes[(axis + 2) % 3])
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    handle: Handle,
+    start: Sphere,
+    frame: Frame,
+    // point on the constraint under the pointer when the drag started
+    anchor: glam::Vec3,
+    // the camera when the drag started, for the uniform scale
+    view_up: glam::Vec3,
+    view_forward: glam::Vec3,
+}
+
+// a polyline, or a convex polygon when filled
+struct Outline {
+    handle: Handle,
+    points: Vec<glam::Vec2>,
+    filled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    pub snapping: bool,
+    // grid spacing of the translation
+    pub translate_step: f32,
+    // radians
+    pub rotate_step: f32,
+    // of the radius
+    pub scale_step: f32,
+    hovered: Option<Handle>,
+    drag: Option<Drag>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            space: GizmoSpace::World,
+            snapping: false,
+            translate_step: 0.25,
+            rotate_step: 15.0_f32.to_radians(),
+            scale_step: 0.05,
+            hovered: None,
+            drag: None,
+        }
+    }
+}
+
+impl Gizmo {
+    // true while the pointer is over a handle or dragging one, clicks
+    // shouldn't change the selection then
+    pub fn is_active(&self) -> bool {
+        self.hovered.is_some() || self.drag.is_some()
+    }
+
+    // forgets the handle under the pointer and the drag, for when the object
+    // they belong to is gone
+    pub fn reset(&mut self) {
+        self.hovered = None;
+        self.drag = None;
+    }
+
+    // handles the pointer over the viewport, returns true when the sphere
+    // changed
+    pub fn update(
+        &mut self,
+        response: &egui::Response,
+        camera: &Camera,
+        sphere: &mut Sphere,
+    ) -> bool {
+        let Some(frame) = Frame::new(camera, sphere, self.space) else {
+            self.reset();
+            return false;
+        };
+
+        let origin = response.rect.min;
+        let viewport_point = |pos: egui::Pos2| glam::vec2(pos.x - origin.x, pos.y - origin.y);
+
+        if response.drag_stopped() {
+            self.drag = None;
+        }
+
+        if self.drag.is_none() {
+            self.hovered = response
+                .hover_pos()
+                .and_then(|pos| self.pick(camera, &frame, viewport_point(pos)));
+        }
+
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            let ray = response
+                .interact_pointer_pos()
+                .and_then(|pos| camera.get_viewport_ray(viewport_point(pos)));
+
+            self.drag = self.hovered.zip(ray).and_then(|(handle, ray)| {
+                let orientation = camera.get_orientation();
+                let view_up = orientation * glam::Vec3::Y;
+                let view_forward = orientation * glam::Vec3::NEG_Z;
+
+                Some(Drag {
+                    handle,
+                    start: *sphere,
+                    frame,
+                    anchor: constraint_point(handle, &frame, view_forward, &ray)?,
+                    view_up,
+                    view_forward,
+                })
+            });
+        }
+
+        let Some(drag) = self.drag else {
+            return false;
+        };
+
+        if !response.dragged_by(egui::PointerButton::Primary) {
+            return false;
+        }
+
+        let Some(ray) = response
+            .interact_pointer_pos()
+            .and_then(|pos| camera.get_viewport_ray(viewport_point(pos)))
+        else {
+            return false;
+        };
+
+        let Some(point) = constraint_point(drag.handle, &drag.frame, drag.view_forward, &ray)
+        else {
+            return false;
+        };
+
+        let before = (sphere.position, sphere.rotation, sphere.radius);
+        self.apply(&drag, point, sphere);
+
+        before != (sphere.position, sphere.rotation, sphere.radius)
+    }
+
+    fn apply(&self, drag: &Drag, point: glam::Vec3, sphere: &mut Sphere) {
+        let start = drag.start;
+        let frame = &drag.frame;
+        let delta = point - drag.anchor;
+
+        match (self.mode, drag.handle) {
+            (GizmoMode::Translate, Handle::Axis(axis) | Handle::Plane(axis)) => {
+                let moved = match drag.handle {
+                    Handle::Plane(_) => [axis != 0, axis != 1, axis != 2],
+                    _ => [axis == 0, axis == 1, axis == 2],
+                };
+
+                sphere.position = start.position;
+
+                for (i, direction) in frame.axes.into_iter().enumerate() {
+                    if !moved[i] {
+                        continue;
+                    }
+
+                    let mut offset = delta.dot(direction);
+
+                    if self.snapping {
+                        offset = match self.space {
+                            // onto the grid rather than by whole steps
+                            GizmoSpace::World => {
+                                let coordinate = start.position.dot(direction);
+                                snap(coordinate + offset, self.translate_step) - coordinate
+                            }
+                            GizmoSpace::Local => snap(offset, self.translate_step),
+                        };
+                    }
+
+                    sphere.position += direction * offset;
+                }
+            }
+            (GizmoMode::Rotate, Handle::Ring(axis)) => {
+                let from = drag.anchor - frame.centre;
+                let to = point - frame.centre;
+                let direction = frame.axes[axis];
+
+                let mut angle = direction.dot(from.cross(to)).atan2(from.dot(to));
+
+                if self.snapping {
+                    angle = snap(angle, self.rotate_step);
+                }
+
+                sphere.rotation =
+                    (glam::Quat::from_axis_angle(direction, angle) * start.rotation).normalize();
+            }
+            (GizmoMode::Scale, Handle::Axis(_) | Handle::Centre) => {
+                let offset = match drag.handle {
+                    Handle::Axis(axis) => delta.dot(frame.axes[axis]),
+                    _ => delta.dot(drag.view_up),
+                };
+
+                // dragging by the length of an axis doubles the radius
+                let mut radius = start.radius * (1.0 + offset / frame.length);
+
+                if self.snapping {
+                    radius = snap(radius, self.scale_step);
+                }
+
+                sphere.radius = radius.max(MIN_RADIUS);
+            }
+            _ => {}
+        }
+    }
+
+    fn pick(&self, camera: &Camera, frame: &Frame, point: glam::Vec2) -> Option<Handle> {
+        self.outlines(camera, frame)
+            .into_iter()
+            .map(|outline| (outline.handle, outline.distance(point)))
+            .filter(|&(_, distance)| distance <= PICK_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(handle, _)| handle)
+    }
+
+    // draws over the viewport in `rect`
+    pub fn draw(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        camera: &Camera,
+        sphere: &Sphere,
+    ) {
+        let Some(frame) = Frame::new(camera, sphere, self.space) else {
+            return;
+        };
+
+        let active = self.drag.map(|drag| drag.handle).or(self.hovered);
+
+        for outline in self.outlines(camera, &frame) {
+            let color = if Some(outline.handle) == active {
+                ACTIVE_COLOR
+            } else {
+                match outline.handle {
+                    Handle::Axis(axis) | Handle::Plane(axis) | Handle::Ring(axis) => {
+                        AXIS_COLORS[axis]
+                    }
+                    Handle::Centre => CENTRE_COLOR,
+                }
+            };
+
+            let points = outline
+                .points
+                .iter()
+                .map(|p| rect.min + egui::vec2(p.x, p.y))
+                .collect::<Vec<_>>();
+
+            if outline.filled {
+                painter.add(egui::Shape::convex_polygon(
+                    points,
+                    color.gamma_multiply(0.4),
+                    egui::Stroke::new(1.0, color),
+                ));
+            } else {
+                painter.add(egui::Shape::line(points, egui::Stroke::new(2.5, color)));
+            }
+        }
+    }
+
+    // the handles of the current mode in viewport space, handles that don't
+    // project are left out
+    fn outlines(&self, camera: &Camera, frame: &Frame) -> Vec<Outline> {
+        let project = |points: &[glam::Vec3]| {
+            points
+                .iter()
+                .map(|&p| camera.project_to_viewport(p))
+                .collect::<Option<Vec<_>>>()
+        };
+
+        let centre = frame.centre;
+        let length = frame.length;
+        let mut shapes = Vec::new();
+
+        match self.mode {
+            GizmoMode::Translate => {
+                for axis in 0..3 {
+                    let (u, v) = frame.plane_axes(axis);
+                    let (near, far) = (0.25 * length, 0.45 * length);
+
+                    shapes.push((
+                        Handle::Plane(axis),
+                        vec![
+                            centre + u * near + v * near,
+                            centre + u * far + v * near,
+                            centre + u * far + v * far,
+                            centre + u * near + v * far,
+                        ],
+                        true,
+                    ));
+                }
+
+                for (axis, direction) in frame.axes.into_iter().enumerate() {
+                    shapes.push((
+                        Handle::Axis(axis),
+                        vec![centre, centre + direction * length],
+                        false,
+                    ));
+                }
+            }
+            GizmoMode::Rotate => {
+                for axis in 0..3 {
+                    let (u, v) = frame.plane_axes(axis);
+
+                    let ring = (0..=RING_SEGMENTS)
+                        .map(|i| {
+                            let angle = i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                            centre + (u * angle.cos() + v * angle.sin()) * length
+                        })
+                        .collect();
+
+                    shapes.push((Handle::Ring(axis), ring, false));
+                }
+            }
+            GizmoMode::Scale => {
+                for (axis, direction) in frame.axes.into_iter().enumerate() {
+                    let tip = centre + direction * length;
+                    let (u, v) = frame.plane_axes(axis);
+                    let half = 0.06 * length;
+
+                    shapes.push((Handle::Axis(axis), vec![centre, tip], false));
+                    shapes.push((
+                        Handle::Axis(axis),
+                        vec![
+                            tip + (u + v) * half,
+                            tip + (u - v) * half,
+                            tip - (u + v) * half,
+                            tip - (u - v) * half,
+                        ],
+                        true,
+                    ));
+                }
+            }
+        }
+
+        let mut outlines = shapes
+            .into_iter()
+            .filter_map(|(handle, points, filled)| {
+                Some(Outline {
+                    handle,
+                    points: project(&points)?,
+                    filled,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // a square that faces the viewer whichever way the object turns
+        if self.mode == GizmoMode::Scale {
+            if let Some(centre) = camera.project_to_viewport(centre) {
+                let half = PICK_DISTANCE;
+
+                outlines.push(Outline {
+                    handle: Handle::Centre,
+                    points: vec![
+                        centre + glam::vec2(-half, -half),
+                        centre + glam::vec2(half, -half),
+                        centre + glam::vec2(half, half),
+                        centre + glam::vec2(-half, half),
+                    ],
+                    filled: true,
+                });
+            }
+        }
+
+        outlines
+    }
+}
+
+impl Outline {
+    // in pixels, zero inside a filled outline
+    fn distance(&self, point: glam::Vec2) -> f32 {
+        let edges = self
+            .points
+            .iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .take(if self.filled {
+                self.points.len()
+            } else {
+                self.points.len().saturating_sub(1)
+            });
+
+        let mut distance = f32::INFINITY;
+        let mut sides = [false; 2];
+
+        for (&a, &b) in edges {
+            let ab = b - a;
+            let t = ((point - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+            distance = distance.min(point.distance(a + ab * t));
+
+            // inside a convex polygon when the point is on the same side of
+            // every edge
+            sides[(ab.perp_dot(point - a) >= 0.0) as usize] = true;
+        }
+
+        if self.filled && !(sides[0] && sides[1]) {
+            return 0.0;
+        }
+
+        distance
+    }
+}
+
+// the point under the ray on what the handle is constrained to
+fn constraint_point(
+    handle: Handle,
+    frame: &Frame,
+    view_forward: glam::Vec3,
+    ray: &Ray,
+) -> Option<glam::Vec3> {
+    match handle {
+        Handle::Axis(axis) => closest_point_on_line(frame.centre, frame.axes[axis], ray),
+        Handle::Plane(axis) | Handle::Ring(axis) => {
+            intersect_plane(frame.centre, frame.axes[axis], ray)
+        }
+        Handle::Centre => intersect_plane(frame.centre, view_forward, ray),
+    }
+}
+
+// `None` when the ray runs along the line
+fn closest_point_on_line(
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    ray: &Ray,
+) -> Option<glam::Vec3> {
+    let ray_direction = ray.direction.normalize();
+    let b = direction.dot(ray_direction);
+    let denominator = 1.0 - b * b;
+
+    if denominator < 1e-6 {
+        return None;
+    }
+
+    let w = origin - ray.origin;
+    let s = (b * ray_direction.dot(w) - direction.dot(w)) / denominator;
+
+    Some(origin + direction * s)
+}
+
+// `None` when the ray is parallel to the plane or points away from it
+fn intersect_plane(point: glam::Vec3, normal: glam::Vec3, ray: &Ray) -> Option<glam::Vec3> {
+    let denominator = normal.dot(ray.direction);
+
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+
+    let t = normal.dot(point - ray.origin) / denominator;
+    (t >= 0.0).then(|| ray.origin + ray.direction * t)
+}
+
+fn snap(value: f32, step: f32) -> f32 {
+    if step > 0.0 {
+        (value / step).round() * step
+    } else {
+        value
+    }
+}
@@ -0,0 +1,276 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use crate::aov::AovFormat;
+use crate::camera::{Camera, Stereo, StereoMode};
+use crate::debug_view::DebugView;
+use crate::image_io;
+use crate::renderer::{RenderSettings, Renderer};
+use crate::world::World;
+
+const USAGE: &str = "usage: realtime-raytracing --headless [--size WIDTHxHEIGHT] [--samples N] \
+                     [--path] [--fps N] [--stereo side-by-side|top-bottom|anaglyph] \
+                     [--debug-view VIEW] [--aovs multilayer|separate] [--denoise STRENGTH] \
+                     [--output PATH]";
+
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub width: u32,
+    pub height: u32,
+    // frames accumulated into every image
+    pub samples: u32,
+    // render the camera path of the scene instead of a single image
+    pub path: bool,
+    pub fps: f32,
+    // for the command line, the viewer exports with the stereo of its camera
+    pub stereo: StereoMode,
+    // for the command line, the viewer exports with its render settings
+    pub debug_view: DebugView,
+    // exr files with the aovs next to every image
+    pub aovs: Option<AovFormat>,
+    // for the command line, strength of the denoiser, `None` keeps the noise
+    pub denoise: Option<f32>,
+    // an image for a single frame, a directory for a sequence
+    pub output: PathBuf,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            width: 900,
+            height: 600,
+            samples: 64,
+            path: false,
+            fps: 24.0,
+            stereo: StereoMode::Off,
+            debug_view: DebugView::Beauty,
+            aovs: None,
+            denoise: None,
+            output: PathBuf::from("render.ppm"),
+        }
+    }
+}
+
+impl HeadlessOptions {
+    // `None` when the arguments ask for the interactive viewer
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut options = Self::default();
+        let mut headless = false;
+        let mut output = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {name}\n{USAGE}"))
+            };
+
+            match arg.as_str() {
+                "--headless" => headless = true,
+                "--path" => options.path = true,
+                "--size" => {
+                    let size = value("--size")?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|&(w, h)| w > 0 && h > 0)
+                        .ok_or_else(|| format!("invalid size: {size}\n{USAGE}"))?;
+
+                    options.width = width;
+                    options.height = height;
+                }
+                "--samples" => {
+                    options.samples = value("--samples")?
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("invalid sample count\n{USAGE}"))?
+                }
+                "--fps" => {
+                    options.fps = value("--fps")?
+                        .parse()
+                        .ok()
+                        .filter(|&n: &f32| n > 0.0)
+                        .ok_or_else(|| format!("invalid frame rate\n{USAGE}"))?
+                }
+                "--stereo" => {
+                    options.stereo = match value("--stereo")?.as_str() {
+                        "side-by-side" => StereoMode::SideBySide,
+                        "top-bottom" => StereoMode::TopBottom,
+                        "anaglyph" => StereoMode::Anaglyph,
+                        mode => return Err(format!("invalid stereo mode: {mode}\n{USAGE}")),
+                    }
+                }
+                "--debug-view" => {
+                    let name = value("--debug-view")?;
+                    options.debug_view = DebugView::from_config_name(&name).ok_or_else(|| {
+                        let names = DebugView::ALL.map(DebugView::config_name).join(", ");
+                        format!("invalid debug view: {name}, expected one of {names}\n{USAGE}")
+                    })?;
+                }
+                "--aovs" => {
+                    let format = value("--aovs")?;
+                    options.aovs = Some(
+                        AovFormat::from_config_name(&format)
+                            .ok_or_else(|| format!("invalid aov format: {format}\n{USAGE}"))?,
+                    );
+                }
+                "--denoise" => {
+                    options.denoise = Some(
+                        value("--denoise")?
+                            .parse()
+                            .ok()
+                            .filter(|&n: &f32| (0.0..=1.0).contains(&n))
+                            .ok_or_else(|| format!("invalid denoise strength\n{USAGE}"))?,
+                    )
+                }
+                "--output" => output = Some(PathBuf::from(value("--output")?)),
+                _ => return Err(format!("unknown argument: {arg}\n{USAGE}")),
+            }
+        }
+
+        if !headless {
+            return Ok(None);
+        }
+
+        options.output = match output {
+            Some(output) => output,
+            None if options.path => PathBuf::from("frames"),
+            None => options.output,
+        };
+
+        Ok(Some(options))
+    }
+}
+
+// renders the demo scene from the command line
+pub fn run(options: &HeadlessOptions) -> std::io::Result<()> {
+    let world = World::demo();
+    let mut camera = Camera::new(45.0_f32.to_radians(), 0.1, 100.0);
+    camera.set_stereo(Stereo {
+        mode: options.stereo,
+        ..Stereo::default()
+    });
+    let mut settings = RenderSettings {
+        debug_view: options.debug_view,
+        aovs: options.aovs.is_some(),
+        ..RenderSettings::default()
+    };
+
+    if let Some(strength) = options.denoise {
+        settings.denoise = true;
+        settings.denoise_strength = strength;
+    }
+
+    if options.path {
+        render_sequence(camera, &world, settings, options, |frame, count| {
+            eprintln!("frame {}/{count}", frame + 1);
+        })
+    } else {
+        let renderer = render_image(&camera, None, &world, settings, options);
+        write_image(&renderer, options, &options.output)
+    }
+}
+
+// walks the camera along the path of the world, writing one image per frame
+// into the output directory
+pub fn render_sequence(
+    mut camera: Camera,
+    world: &World,
+    settings: RenderSettings,
+    options: &HeadlessOptions,
+    mut progress: impl FnMut(usize, usize),
+) -> std::io::Result<()> {
+    if world.camera_path.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "the scene has no camera path",
+        ));
+    }
+
+    std::fs::create_dir_all(&options.output)?;
+
+    let count = (world.camera_path.get_duration() * options.fps) as usize + 1;
+
+    let mut previous = None;
+
+    for frame in 0..count {
+        progress(frame, count);
+
+        let time = frame as f32 / options.fps;
+
+        if let Some(viewpoint) = world.camera_path.evaluate(time, camera.get_up_direction()) {
+            camera.set_viewpoint(viewpoint);
+        }
+
+        let renderer = render_image(&camera, previous.as_ref(), world, settings, options);
+        previous = Some(camera.clone());
+
+        let path = Path::new(&options.output).join(format!("frame_{frame:04}.ppm"));
+        write_image(&renderer, options, &path)?;
+    }
+
+    Ok(())
+}
+
+// `previous` is the camera of the previous frame of a sequence, which the
+// motion aov is measured against
+fn render_image(
+    camera: &Camera,
+    previous: Option<&Camera>,
+    world: &World,
+    settings: RenderSettings,
+    options: &HeadlessOptions,
+) -> Renderer {
+    let resized = |camera: &Camera| {
+        let mut camera = camera.clone();
+        camera.resize(options.height, options.width);
+        camera
+    };
+
+    let camera = resized(camera);
+
+    let mut renderer = Renderer::new();
+    renderer.previous_view_projection =
+        previous.and_then(|previous| resized(previous).get_view_projection());
+    renderer.settings = RenderSettings {
+        accumulate: true,
+        resolution_scale: 1.0,
+        adaptive_resolution: false,
+        // keeps the guides of the denoiser from the first frame on
+        aovs: settings.aovs || settings.denoise,
+        ..settings
+    };
+
+    let rect = egui::Rect::from_min_size(
+        egui::Pos2::ZERO,
+        egui::vec2(options.width as f32, options.height as f32),
+    );
+
+    for sample in 0..options.samples {
+        // only the last frame is written
+        renderer.settings.denoise = settings.denoise && sample + 1 == options.samples;
+        renderer.render(rect, &camera, world);
+    }
+
+    renderer
+}
+
+// the ppm at `path`, and the aovs next to it if the options ask for them
+fn write_image(renderer: &Renderer, options: &HeadlessOptions, path: &Path) -> std::io::Result<()> {
+    // the renderer stores the bottom row first, like the gl texture it feeds
+    let image = renderer
+        .get_texture_data()
+        .chunks(options.width as usize)
+        .rev()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+
+    image_io::write_ppm(path, options.width, options.height, &image)?;
+
+    match (options.aovs, renderer.get_aovs()) {
+        (Some(format), Some(aovs)) => aovs.write(path, format),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,253 @@
+use std::cmp::Ordering;
+
+use crate::world::World;
+
+// the oldest commands are forgotten past this
+const MAX_COMMANDS: usize = 200;
+
+// one edit of the world, undone and redone by swapping in the state on
+// either side of it
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub name: &'static str,
+    before: World,
+    after: World,
+}
+
+#[derive(Debug)]
+pub struct History {
+    commands: Vec<Command>,
+    // the world after the applied commands, where the next edit starts from
+    current: World,
+    // number of commands applied, the ones after it can be redone
+    position: usize,
+    // the last command keeps taking changes until the pointer is released,
+    // so a drag becomes a single command
+    open: bool,
+}
+
+impl History {
+    pub fn new(world: &World) -> Self {
+        Self {
+            commands: vec![],
+            current: world.clone(),
+            position: 0,
+            open: false,
+        }
+    }
+
+    // records the edit that led to `world` if there is one, called only
+    // when something reports an edit, `continuous` while the edit may still
+    // be going on
+    pub fn record(&mut self, world: &World, continuous: bool) {
+        if *world == self.current {
+            self.idle(continuous);
+            return;
+        }
+
+        let before = std::mem::replace(&mut self.current, world.clone());
+        let name = describe(&before, world);
+        let at_end = self.position == self.commands.len();
+
+        match self.commands.last_mut() {
+            Some(command) if self.open && at_end && command.name == name => {
+                command.after = world.clone();
+            }
+            _ => {
+                self.commands.truncate(self.position);
+                self.commands.push(Command {
+                    name,
+                    before,
+                    after: world.clone(),
+                });
+
+                if self.commands.len() > MAX_COMMANDS {
+                    self.commands.remove(0);
+                }
+
+                self.position = self.commands.len();
+            }
+        }
+
+        self.open = continuous;
+    }
+
+    // a frame without edits, the last command stays open while the edit may
+    // still be going on
+    pub fn idle(&mut self, continuous: bool) {
+        self.open &= continuous;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.position < self.commands.len()
+    }
+
+    // returns true if the world changed
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+
+        self.jump_to(self.position - 1, world);
+        true
+    }
+
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        if !self.can_redo() {
+            return false;
+        }
+
+        self.jump_to(self.position + 1, world);
+        true
+    }
+
+    // undoes or redoes until `position` commands are applied
+    pub fn jump_to(&mut self, position: usize, world: &mut World) {
+        let position = position.min(self.commands.len());
+
+        self.current = match position {
+            0 => match self.commands.first() {
+                Some(command) => command.before.clone(),
+                None => return,
+            },
+            _ => self.commands[position - 1].after.clone(),
+        };
+        *world = self.current.clone();
+
+        self.position = position;
+        self.open = false;
+    }
+
+    pub fn get_commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+}
+
+fn describe(before: &World, after: &World) -> &'static str {
+    match before.objects.len().cmp(&after.objects.len()) {
+        Ordering::Less => return "Add object",
+        Ordering::Greater => return "Delete object",
+        Ordering::Equal => {}
+    }
+
+    // deleting a material renumbers the objects using the ones after it
+    match before.materials.len().cmp(&after.materials.len()) {
+        Ordering::Less => return "Add material",
+        Ordering::Greater => return "Delete material",
+        Ordering::Equal => {}
+    }
+
+    if before.objects != after.objects {
+        let pairs = || before.objects.iter().zip(&after.objects);
+
+        let moved = pairs().any(|(a, b)| a.position != b.position);
+        let rotated = pairs().any(|(a, b)| a.rotation != b.rotation);
+        let resized = pairs().any(|(a, b)| a.radius != b.radius);
+        let edited =
+            pairs().any(|(a, b)| a.velocity != b.velocity || a.material_index != b.material_index);
+
+        return match (moved, rotated, resized, edited) {
+            (true, false, false, false) => "Move object",
+            (false, true, false, false) => "Rotate object",
+            (false, false, true, false) => "Resize object",
+            _ => "Edit object",
+        };
+    }
+
+    if before.materials != after.materials {
+        return "Edit material";
+    }
+
+    if before.background != after.background {
+        return "Edit background";
+    }
+
+    if before.bookmarks != after.bookmarks {
+        return match before.bookmarks.len().cmp(&after.bookmarks.len()) {
+            Ordering::Less => "Save view",
+            Ordering::Greater => "Delete view",
+            Ordering::Equal => "Edit views",
+        };
+    }
+
+    let keyframes = |world: &World| world.camera_path.get_keyframes().len();
+    match keyframes(before).cmp(&keyframes(after)) {
+        Ordering::Less => "Add keyframe",
+        Ordering::Greater => "Delete keyframe",
+        Ordering::Equal => "Edit camera path",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continuous_drag_is_one_command() {
+        let mut world = World::demo();
+        let mut history = History::new(&world);
+
+        for _ in 0..5 {
+            world.objects[0].position.x += 0.1;
+            history.record(&world, true);
+        }
+
+        history.idle(false);
+
+        assert_eq!(history.get_commands().len(), 1);
+        assert_eq!(history.get_commands()[0].name, "Move object");
+        assert_eq!(history.get_position(), 1);
+    }
+
+    #[test]
+    fn edit_after_undo_drops_redo() {
+        let mut world = World::demo();
+        let mut history = History::new(&world);
+
+        world.objects[0].position.x += 0.1;
+        history.record(&world, false);
+        world.objects[0].radius = 2.0;
+        history.record(&world, false);
+
+        assert!(history.undo(&mut world));
+        assert!(history.can_redo());
+
+        world.objects.pop();
+        history.record(&world, false);
+
+        let names = history
+            .get_commands()
+            .iter()
+            .map(|command| command.name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["Move object", "Delete object"]);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn jump_to_start_restores_initial_world() {
+        let initial = World::demo();
+        let mut world = initial.clone();
+        let mut history = History::new(&world);
+
+        world.objects[0].position.x += 0.1;
+        history.record(&world, false);
+        world.objects.pop();
+        history.record(&world, false);
+
+        history.jump_to(0, &mut world);
+
+        assert_eq!(world, initial);
+        assert_eq!(history.get_position(), 0);
+        assert!(!history.can_undo());
+    }
+}
@@ -0,0 +1,272 @@
+use std::io::{BufRead, Read};
+use std::path::Path;
+
+// largest number of pixels a loaded image may have
+const MAX_PIXELS: usize = 1 << 28;
+
+// linear float image, rows are stored top to bottom
+pub struct FloatImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<glam::Vec3>,
+}
+
+pub fn read_float_image(path: impl AsRef<Path>) -> std::io::Result<FloatImage> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+
+    match extension.as_deref() {
+        Some("hdr") => read_hdr(path),
+        Some("exr") => read_exr(path),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("unsupported image format: {}", path.display()),
+        )),
+    }
+}
+
+// Radiance RGBE, https://www.graphics.cornell.edu/~bjw/rgbe.html
+pub fn read_hdr(path: impl AsRef<Path>) -> std::io::Result<FloatImage> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid("missing radiance header"));
+    }
+
+    // header lines until an empty one, then the resolution string
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("unexpected end of header"));
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid("only 32-bit_rle_rgbe is supported"));
+            }
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => (
+            h.parse::<usize>().map_err(|_| invalid("invalid height"))?,
+            w.parse::<usize>().map_err(|_| invalid("invalid width"))?,
+        ),
+        _ => return Err(invalid("only -Y h +X w orientation is supported")),
+    };
+
+    if width == 0 || height == 0 {
+        return Err(invalid("empty image"));
+    }
+
+    if width
+        .checked_mul(height)
+        .is_none_or(|pixels| pixels > MAX_PIXELS)
+    {
+        return Err(invalid("image too large"));
+    }
+
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    let mut bytes = data.into_iter();
+    let mut next = || {
+        bytes
+            .next()
+            .ok_or_else(|| invalid("unexpected end of data"))
+    };
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+
+    for _ in 0..height {
+        let header = [next()?, next()?, next()?, next()?];
+
+        let rle = (8..0x8000).contains(&width)
+            && header[0] == 2
+            && header[1] == 2
+            && header[2] & 0x80 == 0;
+
+        if rle {
+            if ((header[2] as usize) << 8 | header[3] as usize) != width {
+                return Err(invalid("scanline width mismatch"));
+            }
+
+            // every channel is run length encoded separately
+            for channel in 0..4 {
+                let mut x = 0;
+
+                while x < width {
+                    let count = next()? as usize;
+
+                    if count > 128 {
+                        let value = next()?;
+                        let count = count - 128;
+
+                        if count > width - x {
+                            return Err(invalid("bad run length"));
+                        }
+
+                        scanline[x..x + count]
+                            .iter_mut()
+                            .for_each(|p| p[channel] = value);
+                        x += count;
+                    } else {
+                        if count == 0 || count > width - x {
+                            return Err(invalid("bad run length"));
+                        }
+
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = next()?;
+                        }
+
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            // flat scanline, the first pixel was already read as the header
+            scanline[0] = header;
+            for pixel in &mut scanline[1..] {
+                *pixel = [next()?, next()?, next()?, next()?];
+            }
+        }
+
+        pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_float(rgbe)));
+    }
+
+    Ok(FloatImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+pub fn read_exr(path: impl AsRef<Path>) -> std::io::Result<FloatImage> {
+    let image = exr::prelude::read_first_rgba_layer_from_file(
+        path,
+        |resolution, _| {
+            (
+                resolution.width(),
+                vec![glam::Vec3::ZERO; resolution.width() * resolution.height()],
+            )
+        },
+        |(width, pixels), position, (r, g, b, _): (f32, f32, f32, f32)| {
+            pixels[position.x() + position.y() * *width] = glam::vec3(r, g, b);
+        },
+    )
+    .map_err(std::io::Error::other)?;
+
+    let (width, pixels) = image.layer_data.channel_data.pixels;
+
+    Ok(FloatImage {
+        width,
+        height: pixels.len() / width.max(1),
+        pixels,
+    })
+}
+
+fn rgbe_to_float([r, g, b, e]: [u8; 4]) -> glam::Vec3 {
+    if e == 0 {
+        return glam::Vec3::ZERO;
+    }
+
+    let f = 2.0_f32.powi(e as i32 - (128 + 8));
+    glam::vec3(r as f32 + 0.5, g as f32 + 0.5, b as f32 + 0.5) * f
+}
+
+// binary ppm from packed rgba pixels, rows top to bottom
+pub fn write_ppm(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+) -> std::io::Result<()> {
+    let mut data = format!("P6\n{width} {height}\n255\n").into_bytes();
+    data.reserve(pixels.len() * 3);
+
+    for &pixel in pixels {
+        data.extend_from_slice(&pixel.to_le_bytes()[..3]);
+    }
+
+    std::fs::write(path, data)
+}
+
+// named float channels of one layer, rows top to bottom
+pub struct ExrLayer {
+    pub name: &'static str,
+    pub channels: Vec<(&'static str, Vec<f32>)>,
+}
+
+// every layer becomes a part of a multi-part exr
+pub fn write_exr(
+    path: impl AsRef<Path>,
+    width: usize,
+    height: usize,
+    layers: Vec<ExrLayer>,
+) -> std::io::Result<()> {
+    use exr::prelude::*;
+
+    let layers = layers
+        .into_iter()
+        .map(|layer| {
+            let channels = layer
+                .channels
+                .into_iter()
+                .map(|(name, samples)| AnyChannel::new(name, FlatSamples::F32(samples)))
+                .collect();
+
+            Layer::new(
+                (width, height),
+                LayerAttributes::named(layer.name),
+                Encoding::FAST_LOSSLESS,
+                AnyChannels::sort(channels),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let attributes = ImageAttributes::new(IntegerBounds::from_dimensions((width, height)));
+
+    Image::from_layers(attributes, layers)
+        .write()
+        .to_file(path)
+        .map_err(std::io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_hdr_rejects_zero_width() {
+        let path = std::env::temp_dir().join("realtime-raytracing-zero-width.hdr");
+        std::fs::write(
+            &path,
+            b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 0\n\x01\x02\x03\x80",
+        )
+        .unwrap();
+
+        let result = read_hdr(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+    }
+}
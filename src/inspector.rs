@@ -0,0 +1,232 @@
+use eframe::egui;
+
+use crate::material::Material;
+use crate::ui::{color_edit, vec3_edit};
+use crate::world::{Sphere, World};
+
+// lists and edits everything in the world, new objects are placed at
+// `spawn_position`. returns true if the world changed
+pub fn inspector(
+    ui: &mut egui::Ui,
+    world: &mut World,
+    selection: &mut Option<usize>,
+    spawn_position: glam::Vec3,
+) -> bool {
+    let mut changed = false;
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.heading("Objects");
+        changed |= object_list(ui, world, selection, spawn_position);
+
+        if let Some(object) = *selection {
+            ui.separator();
+            changed |= object_properties(ui, world, object);
+        }
+
+        ui.separator();
+        ui.heading("Lights");
+        light_list(ui, world, selection);
+
+        ui.separator();
+        ui.heading("Materials");
+        changed |= material_list(ui, world);
+    });
+
+    changed
+}
+
+fn object_name(world: &World, object: usize) -> String {
+    if world.get_material(object).is_emissive() {
+        format!("Sphere {object} (light)")
+    } else {
+        format!("Sphere {object}")
+    }
+}
+
+fn object_list(
+    ui: &mut egui::Ui,
+    world: &mut World,
+    selection: &mut Option<usize>,
+    spawn_position: glam::Vec3,
+) -> bool {
+    let mut changed = false;
+
+    for object in 0..world.objects.len() {
+        let selected = *selection == Some(object);
+        if ui
+            .selectable_label(selected, object_name(world, object))
+            .clicked()
+        {
+            *selection = if selected { None } else { Some(object) };
+        }
+    }
+
+    ui.horizontal(|ui| {
+        if ui.button("Add").clicked() {
+            if world.materials.is_empty() {
+                world
+                    .materials
+                    .push(Material::diffuse(glam::Vec3::splat(0.8)));
+            }
+
+            world.objects.push(Sphere::new(spawn_position, 0.5, 0));
+            *selection = Some(world.objects.len() - 1);
+            changed = true;
+        }
+
+        ui.add_enabled_ui(selection.is_some(), |ui| {
+            if ui.button("Duplicate").clicked() {
+                if let Some(object) = *selection {
+                    // side by side with the original so both stay visible
+                    let mut sphere = world.objects[object];
+                    sphere.position.x += sphere.radius * 2.5;
+
+                    world.objects.push(sphere);
+                    *selection = Some(world.objects.len() - 1);
+                    changed = true;
+                }
+            }
+
+            if ui.button("Delete").clicked() {
+                if let Some(object) = selection.take() {
+                    world.remove_object(object);
+                    changed = true;
+                }
+            }
+        });
+    });
+
+    changed
+}
+
+fn object_properties(ui: &mut egui::Ui, world: &mut World, object: usize) -> bool {
+    let mut changed = false;
+    let material_count = world.materials.len();
+    let sphere = &mut world.objects[object];
+
+    changed |= vec3_edit(ui, "Position", &mut sphere.position, 0.01);
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut sphere.radius, 0.01..=100.0)
+                .text("Radius")
+                .logarithmic(true),
+        )
+        .changed();
+
+    let (x, y, z) = sphere.rotation.to_euler(glam::EulerRot::XYZ);
+    let mut angles = glam::vec3(x, y, z) * 180.0 / std::f32::consts::PI;
+    if vec3_edit(ui, "Rotation", &mut angles, 1.0) {
+        let angles = angles * std::f32::consts::PI / 180.0;
+        sphere.rotation = glam::Quat::from_euler(glam::EulerRot::XYZ, angles.x, angles.y, angles.z);
+        changed = true;
+    }
+
+    changed |= vec3_edit(ui, "Velocity", &mut sphere.velocity, 0.01);
+
+    egui::ComboBox::from_label("Material")
+        .selected_text(format!("Material {}", sphere.material_index))
+        .show_ui(ui, |ui| {
+            for material in 0..material_count {
+                changed |= ui
+                    .selectable_value(
+                        &mut sphere.material_index,
+                        material,
+                        format!("Material {material}"),
+                    )
+                    .changed();
+            }
+        });
+
+    let material_index = sphere.material_index;
+    changed |= material_properties(ui, &mut world.materials[material_index]);
+
+    changed
+}
+
+fn light_list(ui: &mut egui::Ui, world: &World, selection: &mut Option<usize>) {
+    let lights = world.get_emissive_objects().collect::<Vec<_>>();
+
+    if lights.is_empty() {
+        ui.label("No emissive objects");
+    }
+
+    for object in lights {
+        let selected = *selection == Some(object);
+        if ui
+            .selectable_label(selected, object_name(world, object))
+            .clicked()
+        {
+            *selection = if selected { None } else { Some(object) };
+        }
+    }
+
+    if !world.background.is_black() {
+        ui.label("Background");
+    }
+}
+
+fn material_list(ui: &mut egui::Ui, world: &mut World) -> bool {
+    let mut changed = false;
+    let mut duplicate = None;
+    let mut remove = None;
+
+    for index in 0..world.materials.len() {
+        let used = world.is_material_used(index);
+
+        egui::CollapsingHeader::new(format!("Material {index}"))
+            .id_salt(("material", index))
+            .show(ui, |ui| {
+                changed |= material_properties(ui, &mut world.materials[index]);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Duplicate").clicked() {
+                        duplicate = Some(index);
+                    }
+
+                    if ui
+                        .add_enabled(!used, egui::Button::new("Delete"))
+                        .on_disabled_hover_text("Used by an object")
+                        .clicked()
+                    {
+                        remove = Some(index);
+                    }
+                });
+            });
+    }
+
+    if let Some(index) = duplicate {
+        world.materials.push(world.materials[index]);
+        changed = true;
+    }
+
+    if let Some(index) = remove {
+        changed |= world.remove_material(index);
+    }
+
+    if ui.button("Add material").clicked() {
+        world
+            .materials
+            .push(Material::diffuse(glam::Vec3::splat(0.8)));
+        changed = true;
+    }
+
+    changed
+}
+
+fn material_properties(ui: &mut egui::Ui, material: &mut Material) -> bool {
+    let mut changed = false;
+
+    changed |= color_edit(ui, "Albedo", &mut material.albedo);
+    changed |= color_edit(ui, "Emission", &mut material.emission_color);
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut material.emission_strength, 0.0..=100.0)
+                .text("Emission strength")
+                .logarithmic(true),
+        )
+        .changed();
+
+    changed
+}
@@ -4,6 +4,24 @@ mod camera;
 mod state;
 mod ray;
 mod world;
+mod material;
+mod sampling;
+mod background;
+mod ui;
+mod environment;
+mod image_io;
+mod tonemap;
+mod sampler;
+mod filter;
+mod controls;
+mod camera_path;
+mod headless;
+mod inspector;
+mod gizmo;
+mod history;
+mod debug_view;
+mod aov;
+mod denoise;
 
 use state::*;
 
@@ -13,6 +31,22 @@ mod program;
 use program::Program;
 
 fn main() {
+    match headless::HeadlessOptions::from_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => {
+            if let Err(e) = headless::run(&options) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+
     let name = "Realtime Raytracing";
     eframe::run_native(
         name,
@@ -0,0 +1,32 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub albedo: glam::Vec3,
+    pub emission_color: glam::Vec3,
+    pub emission_strength: f32,
+}
+
+impl Material {
+    pub fn diffuse(albedo: glam::Vec3) -> Self {
+        Self {
+            albedo,
+            emission_color: glam::Vec3::ZERO,
+            emission_strength: 0.0,
+        }
+    }
+
+    pub fn emissive(emission_color: glam::Vec3, emission_strength: f32) -> Self {
+        Self {
+            albedo: glam::Vec3::ZERO,
+            emission_color,
+            emission_strength,
+        }
+    }
+
+    pub fn get_emission(&self) -> glam::Vec3 {
+        self.emission_color * self.emission_strength
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission_strength > 0.0 && self.emission_color != glam::Vec3::ZERO
+    }
+}
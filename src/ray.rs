@@ -1,4 +1,7 @@
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin: glam::Vec3,
     pub direction: glam::Vec3,
+    // instant the ray was shot at, as a fraction of the frame interval
+    pub time: f32,
 }
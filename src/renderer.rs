@@ -1,16 +1,159 @@
+use std::f32::consts::PI;
 use std::time::Duration;
 
 use eframe::egui;
 
-use crate::camera::Camera;
+use crate::aov::{AovImage, AovSample};
+use crate::camera::{Camera, CameraSample, Eye, StereoMode};
+use crate::debug_view::DebugView;
+use crate::denoise;
+use crate::filter::{Filter, FilterSampler};
 use crate::ray::Ray;
-use crate::world::World;
+use crate::sampler::SamplePattern;
+use crate::sampling;
+use crate::tonemap::{self, ToneMapper};
+use crate::world::{Hit, World};
 
-const BLACK: glam::Vec4 = glam::vec4(0.0, 0.0, 0.0, 1.0);
+// offset used to keep secondary rays from re-hitting the surface they start on
+const EPSILON: f32 = 1e-4;
+
+const NO_OBJECT: u32 = u32::MAX;
+
+// in display referred srgb
+const SELECTION_COLOR: glam::Vec3 = glam::vec3(1.0, 0.6, 0.1);
+
+// in pixels
+const OUTLINE_WIDTH: i32 = 2;
+
+// frames of history a reprojected accumulation keeps, so a moving camera
+// doesn't drag stale samples along for long
+const MAX_REPROJECTED_FRAMES: u32 = 16;
+
+// relative difference of the view space depth above which a reprojected
+// pixel is taken to see another surface
+const REPROJECTION_DEPTH_TOLERANCE: f32 = 0.05;
+
+// cosine between the normals below which a reprojected pixel is rejected
+const REPROJECTION_NORMAL_TOLERANCE: f32 = 0.9;
+
+// lower bound of the adaptive resolution scale
+const MIN_ADAPTIVE_SCALE: f32 = 0.1;
+
+// the adaptive scale changes in these steps, so the image size and with it
+// the accumulation are kept while the frame rate is close to the target
+const ADAPTIVE_SCALE_STEP: f32 = 0.05;
+
+// frames without camera movement before the adaptive resolution goes back
+// to the full scale
+const IDLE_FRAMES: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    pub accumulate: bool,
+    pub max_bounces: u32,
+    pub samples_per_pixel: u32,
+    pub sample_pattern: SamplePattern,
+    pub filter: Filter,
+    pub tone_mapper: ToneMapper,
+    // in stops, the linear colour is scaled by 2^exposure before tone mapping,
+    // on top of the exposure of a physical camera
+    pub exposure: f32,
+    // fraction of the viewport size the image is traced at, the texture
+    // scales it up
+    pub resolution_scale: f32,
+    // lowers the resolution scale while the camera moves to keep up with
+    // the target frame rate, the image refines once it stops
+    pub adaptive_resolution: bool,
+    pub target_fps: f32,
+    pub debug_view: DebugView,
+    // accumulate the aov buffers next to the beauty image
+    pub aovs: bool,
+    // keeps the accumulated samples that are still visible when the camera
+    // moves, instead of starting over
+    pub reprojection: bool,
+    // filters the displayed beauty image, the aovs guide it
+    pub denoise: bool,
+    pub denoise_strength: f32,
+}
+
+impl RenderSettings {
+    // false when only the way the accumulated image is displayed, or what is
+    // accumulated next to it, changed
+    fn invalidates(&self, previous: &Self) -> bool {
+        let display_only = Self {
+            aovs: previous.aovs,
+            accumulate: previous.accumulate,
+            tone_mapper: previous.tone_mapper,
+            exposure: previous.exposure,
+            reprojection: previous.reprojection,
+            adaptive_resolution: previous.adaptive_resolution,
+            target_fps: previous.target_fps,
+            denoise: previous.denoise,
+            denoise_strength: previous.denoise_strength,
+            ..*self
+        };
+
+        display_only != *previous
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            accumulate: true,
+            max_bounces: 5,
+            samples_per_pixel: 1,
+            sample_pattern: SamplePattern::Sobol,
+            filter: Filter::BlackmanHarris,
+            tone_mapper: ToneMapper::AcesFilmic,
+            exposure: 0.0,
+            resolution_scale: 1.0,
+            adaptive_resolution: false,
+            target_fps: 30.0,
+            debug_view: DebugView::Beauty,
+            aovs: false,
+            reprojection: true,
+            denoise: false,
+            denoise_strength: 0.5,
+        }
+    }
+}
 
 pub struct Renderer {
     image_data: Vec<u32>,
+    // rgb holds the sum of the filter weighted samples, w their count
+    accumulation_data: Vec<glam::Vec4>,
+    // seen through the centre of every pixel
+    surfaces: Vec<Surface>,
+    // empty unless the settings ask for aovs
+    aov_data: Vec<AovSample>,
+    // what the last frame was displayed with, the aovs are exposed the same way
+    exposure: f32,
+    frame_index: u32,
+    // frames rendered, never reset unlike `frame_index`, so the sample
+    // patterns and the random streams move on while the history is reprojected
+    sample_frame: u32,
+    filter_sampler: FilterSampler,
     aspect_ratio: f32,
+    // size of the traced image
+    size: glam::UVec2,
+    pub settings: RenderSettings,
+    // what the accumulated samples were rendered with
+    last_settings: RenderSettings,
+    // of the camera the accumulated samples were seen from, `None` when it
+    // can't be reprojected
+    last_matrices: Option<ViewMatrices>,
+    // since the last frame
+    camera_moved: bool,
+    // of the camera before it last moved, the motion aov is measured against
+    // it, the current camera when `None`
+    pub previous_view_projection: Option<glam::Mat4>,
+    // resolution scale picked by the adaptive resolution while the camera moves
+    adaptive_scale: f32,
+    // rendered since the camera last moved
+    still_frames: u32,
+    // outlined in the image
+    pub selection: Option<usize>,
     pub render_time: Duration,
 }
 
@@ -18,7 +161,23 @@ impl Renderer {
     pub fn new() -> Self {
         Self {
             image_data: vec![],
+            accumulation_data: vec![],
+            surfaces: vec![],
+            aov_data: vec![],
+            exposure: 1.0,
+            frame_index: 1,
+            sample_frame: 0,
+            filter_sampler: FilterSampler::new(RenderSettings::default().filter),
             aspect_ratio: 1.0,
+            size: glam::UVec2::ZERO,
+            settings: RenderSettings::default(),
+            last_settings: RenderSettings::default(),
+            last_matrices: None,
+            camera_moved: false,
+            previous_view_projection: None,
+            adaptive_scale: 1.0,
+            still_frames: 0,
+            selection: None,
             render_time: Duration::ZERO,
         }
     }
@@ -31,78 +190,813 @@ impl Renderer {
 
         self.aspect_ratio = w / h;
 
-        self.image_data.clear();
-        self.image_data.reserve((w * h) as _);
+        let scale = self.resolution_scale();
+        let size = (glam::vec2(w, h) * scale)
+            .round()
+            .as_uvec2()
+            .max(glam::UVec2::ONE);
 
-        let ray_origin = camera.get_position();
-        let mut ray = Ray {
-            origin: *ray_origin,
-            direction: glam::Vec3::ZERO,
-        };
+        let resized = size != self.size;
+        self.size = size;
+
+        let width = self.size.x as usize;
+        let height = self.size.y as usize;
+
+        // the camera of the caller stays at the viewport size for picking
+        let mut camera = camera.clone();
+        camera.resize(height as u32, width as u32);
+        let camera = &camera;
+
+        if self.settings.invalidates(&self.last_settings) {
+            self.frame_index = 1;
+        }
+
+        self.last_settings = self.settings;
+
+        if self.filter_sampler.get_filter() != self.settings.filter {
+            self.filter_sampler = FilterSampler::new(self.settings.filter);
+        }
+
+        // the other views accumulate values that depend on where they are
+        // seen from, the sizes of the views may differ
+        let reproject = self.camera_moved
+            && self.frame_index > 1
+            && self.settings.reprojection
+            && self.settings.debug_view == DebugView::Beauty
+            && self.last_matrices.is_some()
+            && camera.get_view_projection().is_some();
+
+        // a new size with the same pixel count keeps the buffers, but not
+        // what is in them
+        if (self.camera_moved || resized) && !reproject {
+            self.frame_index = 1;
+        }
+
+        self.camera_moved = false;
+
+        let previous = reproject.then(|| {
+            let frames = self.frame_index - 1;
+            let history = frames.min(MAX_REPROJECTED_FRAMES);
+            self.frame_index = history + 1;
+
+            let previous = Reprojection {
+                accumulation: std::mem::take(&mut self.accumulation_data),
+                surfaces: std::mem::take(&mut self.surfaces),
+                matrices: self.last_matrices.unwrap(),
+                scale: history as f32 / frames as f32,
+            };
+
+            self.accumulation_data = vec![glam::Vec4::ZERO; width * height];
+            self.surfaces = vec![Surface::BACKGROUND; width * height];
+
+            // the aovs hold the depth and motion seen from the old view
+            self.aov_data.fill(AovSample::default());
+
+            previous
+        });
+
+        if self.accumulation_data.len() != width * height {
+            self.accumulation_data
+                .resize(width * height, glam::Vec4::ZERO);
+            self.surfaces.resize(width * height, Surface::BACKGROUND);
+            self.frame_index = 1;
+        }
+
+        self.image_data.resize(width * height, 0);
+
+        // the debug views replace what the aovs are taken from
+        let aovs = (self.settings.aovs || self.settings.denoise)
+            && self.settings.debug_view == DebugView::Beauty;
+        let aov_len = if aovs { width * height } else { 0 };
+
+        // the aovs average their own samples, so switching them on or off,
+        // or the denoiser that reads them, keeps the accumulation
+        if self.aov_data.len() != aov_len {
+            self.aov_data = vec![AovSample::default(); aov_len];
+        }
+
+        if self.frame_index == 1 {
+            self.accumulation_data.fill(glam::Vec4::ZERO);
+            self.aov_data.fill(AovSample::default());
+        }
+
+        let lights = world.get_emissive_objects().collect::<Vec<_>>();
+        let settings = self.settings;
+        let exposure = 2.0_f32.powf(settings.exposure) * camera.get_exposure_scale();
+        self.exposure = exposure;
+        let anaglyph = camera.get_stereo().mode == StereoMode::Anaglyph;
+        let forward = *camera.get_direction();
+        let filter_sampler = &self.filter_sampler;
+        let motion = camera.get_view_projection().map(|current| Motion {
+            current,
+            previous: self.previous_view_projection.unwrap_or(current),
+            size: self.size.as_vec2(),
+        });
+
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = height.div_ceil(threads).max(1) * width;
+
+        let frame_index = self.frame_index;
+        let sample_frame = self.sample_frame;
+        self.sample_frame = self.sample_frame.wrapping_add(1);
+
+        // the surfaces only change with the camera or the scene, which both
+        // restart or reproject the accumulation
+        if frame_index == 1 || previous.is_some() {
+            std::thread::scope(|s| {
+                let chunks = self
+                    .surfaces
+                    .chunks_mut(chunk_size)
+                    .zip(self.accumulation_data.chunks_mut(chunk_size))
+                    .enumerate();
+
+                for (chunk_index, (surfaces, accumulation)) in chunks {
+                    let previous = previous.as_ref();
 
-        for y in 0..h as usize {
-            for x in 0..w as usize {
-                ray.direction = camera.get_ray_directions()[x + y * w as usize];
-                let color = self.trace_ray(&ray, world);
+                    s.spawn(move || {
+                        for (i, (surface, accumulated)) in
+                            surfaces.iter_mut().zip(accumulation).enumerate()
+                        {
+                            let index = chunk_index * chunk_size + i;
+                            let pixel = glam::uvec2((index % width) as u32, (index / width) as u32);
 
-                self.image_data.push(utils::convert_to_rgba(&color));
+                            let ray = centre_ray(camera, pixel);
+                            let hit = ray.and_then(|ray| world.intersect(&ray, 0.0, f32::INFINITY));
+                            *surface = Surface::new(camera.get_view(), hit.as_ref());
+
+                            if let (Some(previous), Some(ray)) = (previous, ray) {
+                                *accumulated = previous.lookup(&ray, surface, hit.as_ref());
+                            }
+                        }
+                    });
+                }
+            });
+        }
+
+        let selection = self.selection.map(|object| object as u32);
+        let surfaces = &self.surfaces;
+
+        std::thread::scope(|s| {
+            let chunks = self.accumulation_data.chunks_mut(chunk_size).enumerate();
+            let mut aov_chunks = self.aov_data.chunks_mut(chunk_size);
+
+            for (chunk_index, accumulation) in chunks {
+                let lights = &lights;
+                let mut aov_chunk = aov_chunks.next();
+
+                s.spawn(move || {
+                    let mut rng =
+                        fastrand::Rng::with_seed((sample_frame as u64) << 32 | chunk_index as u64);
+
+                    for (i, accumulated) in accumulation.iter_mut().enumerate() {
+                        let index = chunk_index * chunk_size + i;
+                        let (x, y) = ((index % width) as u32, (index / width) as u32);
+                        let mut aov = aov_chunk.as_deref_mut().map(|aovs| &mut aovs[i]);
+
+                        for sample in 0..settings.samples_per_pixel {
+                            let sample_index = sample_frame
+                                .wrapping_mul(settings.samples_per_pixel)
+                                .wrapping_add(sample);
+
+                            let u = settings.sample_pattern.sample(x, y, sample_index, &mut rng);
+                            let (offset, weight) = filter_sampler.sample(u);
+
+                            let camera_sample = CameraSample {
+                                offset,
+                                lens: sampling::random_vec2(&mut rng),
+                                time: rng.f32(),
+                            };
+
+                            let mut rays = 0;
+                            let mut trace = |ray: Option<Ray>| match ray {
+                                Some(ray) => trace_path(
+                                    ray,
+                                    world,
+                                    lights,
+                                    settings.max_bounces,
+                                    &mut rng,
+                                    &mut rays,
+                                ),
+                                None => PathRadiance::default(),
+                            };
+
+                            let pixel = glam::uvec2(x, y);
+                            let radiance = if !settings.debug_view.traces_paths() {
+                                PathRadiance::default()
+                            } else if anaglyph {
+                                let left = trace(camera.generate_eye_ray(
+                                    pixel,
+                                    camera_sample,
+                                    Some(Eye::Left),
+                                ));
+                                let right = trace(camera.generate_eye_ray(
+                                    pixel,
+                                    camera_sample,
+                                    Some(Eye::Right),
+                                ));
+
+                                PathRadiance::anaglyph(&left, &right)
+                            } else {
+                                trace(camera.generate_ray(pixel, camera_sample))
+                            };
+
+                            if let Some(aov) = aov.as_deref_mut() {
+                                let ray = camera.generate_ray(pixel, camera_sample);
+                                let sample = AovSample {
+                                    emission: radiance.emission,
+                                    direct: radiance.direct,
+                                    indirect: radiance.indirect,
+                                    ..primary_aovs(world, ray, forward, motion.as_ref())
+                                };
+
+                                aov.accumulate(&sample, weight);
+                            }
+
+                            let color = match settings.debug_view {
+                                DebugView::Beauty => radiance.total(),
+                                DebugView::Cost => {
+                                    glam::Vec3::splat((rays as usize * world.objects.len()) as f32)
+                                }
+                                DebugView::Variance => {
+                                    let luminance = tonemap::luminance(radiance.total());
+                                    glam::vec3(luminance, luminance * luminance, 0.0)
+                                }
+                                view => camera
+                                    .generate_ray(pixel, camera_sample)
+                                    .map_or(glam::Vec3::ZERO, |ray| {
+                                        view.trace(&ray, world, forward)
+                                    }),
+                            };
+
+                            *accumulated += glam::Vec4::from((color * weight, 1.0));
+                        }
+                    }
+                });
+            }
+        });
+
+        // the aovs are only there for the beauty image
+        let denoised = (settings.denoise && !self.aov_data.is_empty()).then(|| {
+            let color = self
+                .accumulation_data
+                .iter()
+                .map(average)
+                .collect::<Vec<_>>();
+            let guides = self
+                .aov_data
+                .iter()
+                .map(|aov| aov.resolve(1.0))
+                .collect::<Vec<_>>();
+            let samples = self
+                .accumulation_data
+                .iter()
+                .map(|accumulated| accumulated.w)
+                .collect::<Vec<_>>();
+
+            denoise::denoise(
+                &color,
+                &guides,
+                &samples,
+                width,
+                height,
+                settings.denoise_strength,
+            )
+        });
+
+        std::thread::scope(|s| {
+            for (chunk_index, image) in self.image_data.chunks_mut(chunk_size).enumerate() {
+                let accumulation = &self.accumulation_data;
+                let denoised = denoised.as_deref();
+
+                s.spawn(move || {
+                    for (i, pixel) in image.iter_mut().enumerate() {
+                        let index = chunk_index * chunk_size + i;
+                        let (x, y) = ((index % width) as u32, (index / width) as u32);
+
+                        let color = match denoised {
+                            Some(denoised) => denoised[index],
+                            None => average(&accumulation[index]),
+                        };
+
+                        let mut color = match settings.debug_view {
+                            DebugView::Beauty => tonemap::linear_to_srgb(
+                                settings.tone_mapper.apply(color * exposure),
+                            ),
+                            view => view.display(color),
+                        };
+
+                        if let Some(selection) = selection {
+                            if surfaces[index].object == selection {
+                                color = color.lerp(SELECTION_COLOR, 0.15);
+                            } else if is_outline(surfaces, x, y, width, height, selection) {
+                                color = SELECTION_COLOR;
+                            }
+                        }
+
+                        *pixel = utils::convert_to_rgba(&glam::Vec4::from((color, 1.0)));
+                    }
+                });
             }
+        });
+
+        if self.settings.accumulate {
+            self.frame_index += 1;
+        } else {
+            self.frame_index = 1;
         }
 
+        self.last_matrices = camera
+            .get_view_projection()
+            .map(|view_projection| ViewMatrices {
+                view: *camera.get_view(),
+                view_projection,
+                size: self.size,
+            });
+
         self.render_time = clock.elapsed();
     }
 
-    #[inline]
-    fn trace_ray(&self, ray: &Ray, world: &World) -> glam::Vec4 {
-        if world.objects.is_empty() {
-            return BLACK;
+    // the fraction of the viewport size the next frame is traced at
+    fn resolution_scale(&mut self) -> f32 {
+        let full = self.settings.resolution_scale.clamp(0.05, 1.0);
+
+        if self.camera_moved {
+            self.still_frames = 0;
+        } else {
+            self.still_frames = self.still_frames.saturating_add(1);
         }
 
-        let Some((t, sphere)) = world
-            .objects
-            .iter()
-            .filter_map(|sphere| {
-                let origin = ray.origin - sphere.position;
-                let a = ray.direction.dot(ray.direction);
-                let b = 2.0 * origin.dot(ray.direction);
-                let c = origin.dot(origin) - sphere.radius * sphere.radius;
+        if !self.settings.adaptive_resolution || self.still_frames >= IDLE_FRAMES {
+            self.adaptive_scale = full;
+            return full;
+        }
 
-                // b^2 - 4ac
-                let discriminant = b * b - 4.0 * a * c;
-                if discriminant < 0.0 {
-                    return None;
-                }
+        // the time of a frame grows with the number of pixels, the square
+        // of the scale
+        let frame_time = self.render_time.as_secs_f32();
+        let target = 1.0 / self.settings.target_fps.max(1.0);
 
-                let d_sqrt = discriminant.sqrt();
-                let t = (-b - d_sqrt) / (2.0 * a);
-                Some((t, sphere))
-            })
-            .min_by(|(t1, _), (t2, _)| t1.total_cmp(t2))
-        else {
-            return BLACK;
-        };
+        if frame_time > 0.0 {
+            let ideal = self.adaptive_scale * (target / frame_time).sqrt();
 
-        let light_direction = glam::vec3(-1.0, -1.0, -1.0);
+            if (ideal - self.adaptive_scale).abs() > ADAPTIVE_SCALE_STEP {
+                // half way there, the frame times are noisy
+                let scale = self.adaptive_scale + (ideal - self.adaptive_scale) * 0.5;
+                self.adaptive_scale = (scale / ADAPTIVE_SCALE_STEP).round() * ADAPTIVE_SCALE_STEP;
+            }
+        }
 
-        let origin = ray.origin - sphere.position;
-        let h0 = origin + t * ray.direction;
+        self.adaptive_scale = self
+            .adaptive_scale
+            .clamp(MIN_ADAPTIVE_SCALE.min(full), full);
+        self.adaptive_scale
+    }
 
-        let normal = h0.normalize();
-        let light_intensity = normal.dot(-light_direction).max(0.0) / light_direction.length();
+    pub fn reset_frame_index(&mut self) {
+        self.frame_index = 1;
+    }
 
-        glam::Vec4::from((sphere.albedo * light_intensity, 1.0))
+    // the next frame reprojects the accumulated samples into the new view
+    // when the settings allow it, and starts over otherwise
+    pub fn camera_moved(&mut self) {
+        self.camera_moved = true;
+        self.previous_view_projection = self.last_matrices.map(|matrices| matrices.view_projection);
+    }
+
+    pub fn get_frame_index(&self) -> u32 {
+        self.frame_index
+    }
+
+    pub fn get_size(&self) -> glam::UVec2 {
+        self.size
     }
 
     pub fn get_texture_data(&self) -> &[u32] {
         &self.image_data
     }
+
+    // the resolved aov buffers of the last frame, `None` unless they were
+    // rendered
+    pub fn get_aovs(&self) -> Option<AovImage> {
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+
+        if self.aov_data.is_empty() || self.aov_data.len() != width * height {
+            return None;
+        }
+
+        // the buffers are stored bottom row first
+        let indices = || {
+            (0..height)
+                .rev()
+                .flat_map(move |y| (y * width)..((y + 1) * width))
+        };
+
+        // not denoised, that is left to the compositor
+        let beauty = indices()
+            .map(|i| average(&self.accumulation_data[i]) * self.exposure)
+            .collect();
+
+        let object_ids = indices()
+            .map(|i| match self.surfaces[i].object {
+                NO_OBJECT => -1.0,
+                id => id as f32,
+            })
+            .collect();
+
+        Some(AovImage {
+            width,
+            height,
+            beauty,
+            samples: indices()
+                .map(|i| self.aov_data[i].resolve(self.exposure))
+                .collect(),
+            object_ids,
+        })
+    }
+}
+
+// average of the filter weighted samples
+fn average(accumulated: &glam::Vec4) -> glam::Vec3 {
+    if accumulated.w > 0.0 {
+        accumulated.truncate() / accumulated.w
+    } else {
+        glam::Vec3::ZERO
+    }
+}
+
+// pinhole ray through the pixel centre at the middle of the shutter interval
+fn centre_ray(camera: &Camera, pixel: glam::UVec2) -> Option<Ray> {
+    let sample = CameraSample {
+        offset: glam::Vec2::ZERO,
+        lens: glam::vec2(0.5, 0.5),
+        time: 0.5,
+    };
+
+    camera.generate_ray(pixel, sample)
+}
+
+// what the centre of a pixel sees
+#[derive(Debug, Clone, Copy)]
+struct Surface {
+    // `NO_OBJECT` for the background
+    object: u32,
+    // in view space, along the view direction
+    depth: f32,
+    normal: glam::Vec3,
+}
+
+impl Surface {
+    const BACKGROUND: Surface = Surface {
+        object: NO_OBJECT,
+        depth: 0.0,
+        normal: glam::Vec3::ZERO,
+    };
+
+    fn new(view: &glam::Mat4, hit: Option<&Hit>) -> Self {
+        match hit {
+            Some(hit) => Surface {
+                object: hit.object_index as u32,
+                depth: -view.transform_point3(hit.position).z,
+                normal: hit.normal,
+            },
+            None => Surface::BACKGROUND,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ViewMatrices {
+    view: glam::Mat4,
+    view_projection: glam::Mat4,
+    // of the image rendered with them
+    size: glam::UVec2,
+}
+
+// the accumulation of the previous view, and what its pixels saw
+struct Reprojection {
+    accumulation: Vec<glam::Vec4>,
+    surfaces: Vec<Surface>,
+    matrices: ViewMatrices,
+    // of the accumulated values, to cut the history short
+    scale: f32,
+}
+
+impl Reprojection {
+    // the accumulated value of the previous view where it saw what the pixel
+    // centre `ray` sees now, zero where it didn't
+    fn lookup(&self, ray: &Ray, surface: &Surface, hit: Option<&Hit>) -> glam::Vec4 {
+        // the background only depends on the direction
+        let point = match hit {
+            Some(hit) => hit.position.extend(1.0),
+            None => ray.direction.extend(0.0),
+        };
+
+        let clip = self.matrices.view_projection * point;
+        if clip.w <= 0.0 {
+            return glam::Vec4::ZERO;
+        }
+
+        // the buffers store the bottom row first
+        let size = self.matrices.size;
+        let position = (glam::vec2(clip.x, clip.y) / clip.w * 0.5 + 0.5) * size.as_vec2();
+        if position.cmplt(glam::Vec2::ZERO).any() || position.cmpge(size.as_vec2()).any() {
+            return glam::Vec4::ZERO;
+        }
+
+        let (x, y) = (position.x as usize, position.y as usize);
+        let (width, height) = (size.x as usize, size.y as usize);
+        let index = x + y * width;
+        let before = &self.surfaces[index];
+
+        // the pixel filter blends the pixels along a silhouette with what is
+        // on the other side of it
+        let edge = (y.saturating_sub(1)..(y + 2).min(height)).any(|ny| {
+            (x.saturating_sub(1)..(x + 2).min(width))
+                .any(|nx| self.surfaces[nx + ny * width].object != before.object)
+        });
+
+        let visible = !edge
+            && match hit {
+                Some(hit) => {
+                    let depth = -self.matrices.view.transform_point3(hit.position).z;
+
+                    before.object == surface.object
+                        && (before.depth - depth).abs() <= REPROJECTION_DEPTH_TOLERANCE * depth
+                        && before.normal.dot(surface.normal) >= REPROJECTION_NORMAL_TOLERANCE
+                }
+                None => before.object == NO_OBJECT,
+            };
+
+        if visible {
+            self.accumulation[index] * self.scale
+        } else {
+            glam::Vec4::ZERO
+        }
+    }
+}
+
+// pixels outside of the selected object but close enough to its edge
+fn is_outline(
+    surfaces: &[Surface],
+    x: u32,
+    y: u32,
+    width: usize,
+    height: usize,
+    selection: u32,
+) -> bool {
+    let (x, y) = (x as i32, y as i32);
+
+    (-OUTLINE_WIDTH..=OUTLINE_WIDTH).any(|dy| {
+        (-OUTLINE_WIDTH..=OUTLINE_WIDTH).any(|dx| {
+            let (nx, ny) = (x + dx, y + dy);
+
+            nx >= 0
+                && ny >= 0
+                && (nx as usize) < width
+                && (ny as usize) < height
+                && surfaces[ny as usize * width + nx as usize].object == selection
+        })
+    })
+}
+
+// radiance of a path split by the number of surfaces the light scattered off
+// on its way to the camera
+#[derive(Debug, Clone, Copy, Default)]
+struct PathRadiance {
+    emission: glam::Vec3,
+    direct: glam::Vec3,
+    indirect: glam::Vec3,
+}
+
+impl PathRadiance {
+    fn add(&mut self, scatterings: u32, radiance: glam::Vec3) {
+        match scatterings {
+            0 => self.emission += radiance,
+            1 => self.direct += radiance,
+            _ => self.indirect += radiance,
+        }
+    }
+
+    fn total(&self) -> glam::Vec3 {
+        self.emission + self.direct + self.indirect
+    }
+
+    // red from the left eye, green and blue from the right one
+    fn anaglyph(left: &PathRadiance, right: &PathRadiance) -> PathRadiance {
+        let mix = |l: glam::Vec3, r: glam::Vec3| glam::vec3(l.x, r.y, r.z);
+
+        PathRadiance {
+            emission: mix(left.emission, right.emission),
+            direct: mix(left.direct, right.direct),
+            indirect: mix(left.indirect, right.indirect),
+        }
+    }
+}
+
+// geometry of the first hit of a sample, the lighting is filled in from the
+// traced path
+fn primary_aovs(
+    world: &World,
+    ray: Option<Ray>,
+    forward: glam::Vec3,
+    motion: Option<&Motion>,
+) -> AovSample {
+    let Some((ray, hit)) =
+        ray.and_then(|ray| Some((ray, world.intersect(&ray, 0.0, f32::INFINITY)?)))
+    else {
+        return AovSample::default();
+    };
+
+    AovSample {
+        depth: (hit.position - ray.origin).dot(forward),
+        normal: hit.normal,
+        albedo: world.get_material(hit.object_index).albedo,
+        motion: motion.map_or(glam::Vec2::ZERO, |motion| motion.of(hit.position)),
+        ..AovSample::default()
+    }
+}
+
+// the projections of the current and the previous frame, for the motion aov
+#[derive(Debug, Clone, Copy)]
+struct Motion {
+    current: glam::Mat4,
+    previous: glam::Mat4,
+    // of the image, in pixels
+    size: glam::Vec2,
+}
+
+impl Motion {
+    // in pixels, x right and y down, of a point from the previous frame to
+    // this one. the velocities of the spheres only blur within a frame, the
+    // world doesn't move between the frames
+    fn of(&self, point: glam::Vec3) -> glam::Vec2 {
+        let project = |view_projection: &glam::Mat4, point: glam::Vec3| {
+            let clip = *view_projection * point.extend(1.0);
+            (clip.w > 0.0).then(|| {
+                let ndc = glam::vec2(clip.x, clip.y) / clip.w;
+                glam::vec2(ndc.x + 1.0, 1.0 - ndc.y) / 2.0 * self.size
+            })
+        };
+
+        project(&self.current, point)
+            .zip(project(&self.previous, point))
+            .map_or(glam::Vec2::ZERO, |(now, before)| now - before)
+    }
+}
+
+// unidirectional path tracer with next event estimation towards emissive
+// spheres and the background, combined with the bsdf samples through the
+// power heuristic
+fn trace_path(
+    mut ray: Ray,
+    world: &World,
+    lights: &[usize],
+    max_bounces: u32,
+    rng: &mut fastrand::Rng,
+    // counts the rays cast into the world
+    rays: &mut u32,
+) -> PathRadiance {
+    let mut radiance = PathRadiance::default();
+    let mut throughput = glam::Vec3::ONE;
+
+    // the background is treated as one more light when picking a light for
+    // next event estimation
+    let sample_background = !world.background.is_black();
+    let light_count = lights.len() + sample_background as usize;
+
+    // pdf of the bsdf sample that spawned `ray`, `None` for camera rays
+    let mut bsdf_pdf: Option<f32> = None;
+
+    for bounce in 0..=max_bounces {
+        *rays += 1;
+        let Some(hit) = world.intersect(&ray, EPSILON, f32::INFINITY) else {
+            let weight = match bsdf_pdf {
+                None => 1.0,
+                Some(_) if !sample_background => 1.0,
+                Some(bsdf_pdf) => {
+                    let light_pdf = world.background.pdf(ray.direction) / light_count as f32;
+                    sampling::power_heuristic(bsdf_pdf, light_pdf)
+                }
+            };
+
+            radiance.add(
+                bounce,
+                throughput * world.background.radiance(ray.direction) * weight,
+            );
+            break;
+        };
+
+        let material = world.get_material(hit.object_index);
+        let front_face = hit.normal.dot(ray.direction) < 0.0;
+        let normal = if front_face { hit.normal } else { -hit.normal };
+
+        if front_face && material.is_emissive() {
+            let weight = match bsdf_pdf {
+                None => 1.0,
+                Some(bsdf_pdf) => {
+                    let light_pdf = world.objects[hit.object_index]
+                        .solid_angle_pdf(ray.origin, ray.time)
+                        / light_count as f32;
+
+                    sampling::power_heuristic(bsdf_pdf, light_pdf)
+                }
+            };
+
+            radiance.add(bounce, throughput * material.get_emission() * weight);
+        }
+
+        if bounce == max_bounces || material.albedo == glam::Vec3::ZERO {
+            break;
+        }
+
+        let brdf = material.albedo / PI;
+
+        // next event estimation
+        if light_count > 0 {
+            let light = rng.usize(..light_count);
+
+            let sample = if light == lights.len() {
+                let (direction, emission, pdf) = world.background.sample(rng);
+                Some((direction, emission, pdf, None))
+            } else {
+                let light_index = lights[light];
+                let emission = world.get_material(light_index).get_emission();
+
+                (light_index != hit.object_index)
+                    .then(|| {
+                        world.objects[light_index].sample_solid_angle(
+                            hit.position,
+                            ray.time,
+                            sampling::random_vec2(rng),
+                        )
+                    })
+                    .flatten()
+                    .map(|(direction, pdf)| (direction, emission, pdf, Some(light_index)))
+            };
+
+            if let Some((direction, emission, light_pdf, target)) = sample {
+                let cos_theta = normal.dot(direction);
+                *rays += (cos_theta > 0.0 && light_pdf > 0.0) as u32;
+
+                let shadow_ray = Ray {
+                    origin: hit.position + normal * EPSILON,
+                    direction,
+                    time: ray.time,
+                };
+
+                // the shadow ray has to reach the sampled sphere, or escape
+                // the scene when the background was sampled
+                let visible = cos_theta > 0.0
+                    && light_pdf > 0.0
+                    && world
+                        .intersect(&shadow_ray, EPSILON, f32::INFINITY)
+                        .map(|shadow_hit| shadow_hit.object_index)
+                        == target;
+
+                if visible {
+                    let light_pdf = light_pdf / light_count as f32;
+                    let weight = sampling::power_heuristic(
+                        light_pdf,
+                        sampling::cosine_hemisphere_pdf(cos_theta),
+                    );
+
+                    radiance.add(
+                        bounce + 1,
+                        throughput * brdf * emission * (cos_theta * weight / light_pdf),
+                    );
+                }
+            }
+        }
+
+        // bsdf sampling, for a cosine weighted lambertian lobe f * cos / pdf = albedo
+        let direction = sampling::cosine_hemisphere(normal, sampling::random_vec2(rng));
+        bsdf_pdf = Some(sampling::cosine_hemisphere_pdf(normal.dot(direction)));
+        throughput *= material.albedo;
+
+        ray = Ray {
+            origin: hit.position + normal * EPSILON,
+            direction,
+            time: ray.time,
+        };
+
+        // russian roulette
+        if bounce >= 3 {
+            let p = throughput.max_element().min(0.95);
+            if rng.f32() >= p {
+                break;
+            }
+
+            throughput /= p;
+        }
+    }
+
+    radiance
 }
 
 mod utils {
     pub fn convert_to_rgba(color: &glam::Vec4) -> u32 {
-        ((color.w * 255.0) as u32) << 24
-            | ((color.z * 255.0) as u32) << 16
-            | ((color.y * 255.0) as u32) << 8
-            | ((color.x * 255.0) as u32)
+        let color = (color.clamp(glam::Vec4::ZERO, glam::Vec4::ONE) * 255.0).round();
+
+        (color.w as u32) << 24 | (color.z as u32) << 16 | (color.y as u32) << 8 | (color.x as u32)
     }
 }
@@ -0,0 +1,103 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplePattern {
+    Random,
+    Stratified,
+    Sobol,
+    BlueNoise,
+}
+
+// side of the jittered grid the stratified pattern cycles through
+const STRATA: u32 = 4;
+
+impl SamplePattern {
+    pub const ALL: [SamplePattern; 4] = [
+        SamplePattern::Random,
+        SamplePattern::Stratified,
+        SamplePattern::Sobol,
+        SamplePattern::BlueNoise,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SamplePattern::Random => "Random",
+            SamplePattern::Stratified => "Stratified",
+            SamplePattern::Sobol => "Sobol",
+            SamplePattern::BlueNoise => "Blue noise",
+        }
+    }
+
+    // 2d sample in [0, 1) for the `index`th sample of pixel (x, y), successive
+    // indices of the same pixel are well distributed over the pixel area
+    pub fn sample(self, x: u32, y: u32, index: u32, rng: &mut fastrand::Rng) -> glam::Vec2 {
+        match self {
+            SamplePattern::Random => glam::vec2(rng.f32(), rng.f32()),
+            SamplePattern::Stratified => {
+                // visit the strata in a different order for every pixel
+                let count = STRATA * STRATA;
+                let stratum = (index.wrapping_add(hash(x, y))) % count;
+
+                let cell = glam::vec2((stratum % STRATA) as f32, (stratum / STRATA) as f32);
+                (cell + glam::vec2(rng.f32(), rng.f32())) / STRATA as f32
+            }
+            SamplePattern::Sobol => {
+                // random digit scrambling keeps the (0, 2) sequence property
+                let scramble = hash(x, y);
+                let scramble_y = hash(scramble, 1);
+
+                glam::vec2(
+                    to_unit_float(index.reverse_bits() ^ scramble),
+                    to_unit_float(sobol_second_dimension(index) ^ scramble_y),
+                )
+            }
+            SamplePattern::BlueNoise => {
+                // R2 low discrepancy sequence over the samples, rotated by
+                // interleaved gradient noise so the error is blue across pixels
+                const G: f64 = 1.324_717_957_244_746;
+                let alpha = glam::dvec2(1.0 / G, 1.0 / (G * G));
+                let offset = glam::dvec2(
+                    interleaved_gradient_noise(x as f32, y as f32) as f64,
+                    interleaved_gradient_noise(y as f32 + 47.0, x as f32 + 17.0) as f64,
+                );
+
+                (offset + alpha * index as f64).fract().as_vec2()
+            }
+        }
+    }
+}
+
+// generator matrix of the second sobol dimension, the first one is the van der
+// corput sequence which is just the reversed bits of the index
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut v = 1 << 31;
+    let mut result = 0;
+
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+
+        index >>= 1;
+        v ^= v >> 1;
+    }
+
+    result
+}
+
+// Jimenez 2014, "Next Generation Post Processing in Call of Duty: Advanced Warfare"
+fn interleaved_gradient_noise(x: f32, y: f32) -> f32 {
+    (52.982_918 * (0.067_110_56 * x + 0.005_837_15 * y).fract()).fract()
+}
+
+fn to_unit_float(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1 << 24) as f32
+}
+
+// integer hash with good avalanche, used to decorrelate pixels
+fn hash(a: u32, b: u32) -> u32 {
+    let mut h = a.wrapping_mul(0x9e37_79b9) ^ b.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
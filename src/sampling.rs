@@ -0,0 +1,107 @@
+use std::f32::consts::PI;
+
+// smallest 1 - cos(theta_max) of a cone, far away or tiny lights round to a
+// cosine of one and would get an infinite pdf
+const MIN_CONE_EXTENT: f32 = 1e-7;
+
+pub fn random_vec2(rng: &mut fastrand::Rng) -> glam::Vec2 {
+    glam::vec2(rng.f32(), rng.f32())
+}
+
+// Duff et al. 2017, "Building an Orthonormal Basis, Revisited"
+pub fn orthonormal_basis(n: glam::Vec3) -> (glam::Vec3, glam::Vec3) {
+    let sign = 1.0_f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+
+    (
+        glam::vec3(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        glam::vec3(b, sign + n.y * n.y * a, -n.y),
+    )
+}
+
+pub fn to_world(local: glam::Vec3, n: glam::Vec3) -> glam::Vec3 {
+    let (t, b) = orthonormal_basis(n);
+    t * local.x + b * local.y + n * local.z
+}
+
+pub fn cosine_hemisphere(n: glam::Vec3, u: glam::Vec2) -> glam::Vec3 {
+    let r = u.x.sqrt();
+    let phi = 2.0 * PI * u.y;
+    let local = glam::vec3(r * phi.cos(), r * phi.sin(), (1.0 - u.x).max(0.0).sqrt());
+
+    to_world(local, n)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta.max(0.0) / PI
+}
+
+// Shirley and Chiu 1997, "A Low Distortion Map Between Disk and Square"
+pub fn concentric_disk(u: glam::Vec2) -> glam::Vec2 {
+    let offset = u * 2.0 - 1.0;
+
+    if offset == glam::Vec2::ZERO {
+        return glam::Vec2::ZERO;
+    }
+
+    let (r, theta) = if offset.x.abs() > offset.y.abs() {
+        (offset.x, PI / 4.0 * (offset.y / offset.x))
+    } else {
+        (offset.y, PI / 2.0 - PI / 4.0 * (offset.x / offset.y))
+    };
+
+    r * glam::vec2(theta.cos(), theta.sin())
+}
+
+// uniform point inside a regular polygon inscribed in the unit circle
+pub fn regular_polygon(u: glam::Vec2, sides: u32, rotation: f32) -> glam::Vec2 {
+    // pick one of the triangles fanning out from the centre, reusing u.x
+    let scaled = u.x * sides as f32;
+    let side = (scaled as u32).min(sides - 1);
+    let u = glam::vec2(scaled - side as f32, u.y);
+
+    let angle = |i: u32| rotation + 2.0 * PI * i as f32 / sides as f32;
+    let a = glam::Vec2::from_angle(angle(side));
+    let b = glam::Vec2::from_angle(angle(side + 1));
+
+    // uniform barycentrics, the centre is the third vertex
+    let su = u.x.sqrt();
+    a * (su * (1.0 - u.y)) + b * (su * u.y)
+}
+
+pub fn uniform_sphere(u: glam::Vec2) -> glam::Vec3 {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.y;
+
+    glam::vec3(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_sphere_pdf() -> f32 {
+    1.0 / (4.0 * PI)
+}
+
+pub fn uniform_cone(axis: glam::Vec3, cos_theta_max: f32, u: glam::Vec2) -> glam::Vec3 {
+    let cos_theta = 1.0 - u.x * (1.0 - cos_theta_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.y;
+    let local = glam::vec3(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+
+    to_world(local, axis)
+}
+
+pub fn uniform_cone_pdf(cos_theta_max: f32) -> f32 {
+    1.0 / (2.0 * PI * (1.0 - cos_theta_max).max(MIN_CONE_EXTENT))
+}
+
+pub fn power_heuristic(pdf_a: f32, pdf_b: f32) -> f32 {
+    let a = pdf_a * pdf_a;
+    let b = pdf_b * pdf_b;
+
+    if a + b == 0.0 {
+        return 0.0;
+    }
+
+    a / (a + b)
+}
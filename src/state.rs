@@ -2,14 +2,22 @@
 pub struct PointerState {
     pub pos: Option<glam::Vec2>,
     pub secondary_down: bool,
+    pub middle_down: bool,
+    // scrolled points this frame, positive away from the user
+    pub scroll: f32,
 }
 
+// held movement actions, resolved from the key bindings
 #[derive(Debug, Clone, Copy)]
 pub struct KeyboardState {
-    pub w: bool,
-    pub a: bool,
-    pub s: bool,
-    pub d: bool,
-    pub q: bool,
-    pub e: bool,
+    pub forward: bool,
+    pub backward: bool,
+    pub left: bool,
+    pub right: bool,
+    pub down: bool,
+    pub up: bool,
+    pub roll_left: bool,
+    pub roll_right: bool,
+    pub boost: bool,
+    pub slow: bool,
 }
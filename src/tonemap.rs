@@ -0,0 +1,129 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapper {
+    Clamp,
+    Reinhard,
+    AcesFilmic,
+    AgX,
+    Uncharted2,
+}
+
+impl ToneMapper {
+    pub const ALL: [ToneMapper; 5] = [
+        ToneMapper::Clamp,
+        ToneMapper::Reinhard,
+        ToneMapper::AcesFilmic,
+        ToneMapper::AgX,
+        ToneMapper::Uncharted2,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapper::Clamp => "None (clamp)",
+            ToneMapper::Reinhard => "Reinhard",
+            ToneMapper::AcesFilmic => "ACES filmic",
+            ToneMapper::AgX => "AgX",
+            ToneMapper::Uncharted2 => "Uncharted 2",
+        }
+    }
+
+    // maps scene referred linear colour to display referred linear colour in [0, 1]
+    pub fn apply(self, color: glam::Vec3) -> glam::Vec3 {
+        let color = color.max(glam::Vec3::ZERO);
+
+        let mapped = match self {
+            ToneMapper::Clamp => color,
+            ToneMapper::Reinhard => color / (1.0 + color),
+            ToneMapper::AcesFilmic => aces_filmic(color),
+            ToneMapper::AgX => agx(color),
+            ToneMapper::Uncharted2 => uncharted2(color),
+        };
+
+        mapped.clamp(glam::Vec3::ZERO, glam::Vec3::ONE)
+    }
+}
+
+// rec. 709 weights
+pub fn luminance(color: glam::Vec3) -> f32 {
+    color.dot(glam::vec3(0.2126, 0.7152, 0.0722))
+}
+
+pub fn linear_to_srgb(color: glam::Vec3) -> glam::Vec3 {
+    let encode = |c: f32| {
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    };
+
+    glam::vec3(encode(color.x), encode(color.y), encode(color.z))
+}
+
+// Stephen Hill's fit of the ACES RRT + ODT
+fn aces_filmic(color: glam::Vec3) -> glam::Vec3 {
+    let input = glam::mat3(
+        glam::vec3(0.59719, 0.35458, 0.04823),
+        glam::vec3(0.07600, 0.90834, 0.01566),
+        glam::vec3(0.02840, 0.13383, 0.83777),
+    )
+    .transpose();
+
+    let output = glam::mat3(
+        glam::vec3(1.60475, -0.53108, -0.07367),
+        glam::vec3(-0.10208, 1.10813, -0.00605),
+        glam::vec3(-0.00327, -0.07276, 1.07602),
+    )
+    .transpose();
+
+    let v = input * color;
+    let a = v * (v + 0.0245786) - 9.0537e-05;
+    let b = v * (0.983729 * v + 0.432951) + 0.238081;
+
+    output * (a / b)
+}
+
+// Benjamin Wrensch's minimal AgX with the default look
+fn agx(color: glam::Vec3) -> glam::Vec3 {
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+
+    let inset = glam::mat3(
+        glam::vec3(0.84247906, 0.042328242, 0.042375655),
+        glam::vec3(0.0784336, 0.87846864, 0.0784336),
+        glam::vec3(0.079223745, 0.07916613, 0.879143),
+    );
+
+    let outset = glam::mat3(
+        glam::vec3(1.196879, -0.052896852, -0.052971636),
+        glam::vec3(-0.09802088, 1.1519031, -0.09804345),
+        glam::vec3(-0.09902974, -0.098961177, 1.1510737),
+    );
+
+    let v = inset * color;
+    let v = glam::vec3(v.x.log2(), v.y.log2(), v.z.log2())
+        .clamp(glam::Vec3::splat(MIN_EV), glam::Vec3::splat(MAX_EV));
+    let x = (v - MIN_EV) / (MAX_EV - MIN_EV);
+
+    // sigmoid contrast curve, 6th order polynomial approximation
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    let v =
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232;
+
+    // the curve outputs display encoded values, bring them back to linear
+    (outset * v).max(glam::Vec3::ZERO).powf(2.2)
+}
+
+// John Hable's filmic curve
+fn uncharted2(color: glam::Vec3) -> glam::Vec3 {
+    const WHITE_POINT: f32 = 11.2;
+    const EXPOSURE_BIAS: f32 = 2.0;
+
+    let curve = |x: glam::Vec3| {
+        let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+        ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+    };
+
+    curve(color * EXPOSURE_BIAS) / curve(glam::Vec3::splat(WHITE_POINT))
+}
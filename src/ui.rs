@@ -0,0 +1,672 @@
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::background::{Background, BackgroundMode, PhysicalSky};
+use crate::camera::{
+    FisheyeMapping, Lens, PhysicalCamera, Projection, Shutter, Stereo, StereoMode,
+};
+use crate::controls::{Action, Controls};
+use crate::debug_view::DebugView;
+use crate::environment::EnvironmentMap;
+use crate::filter::Filter;
+use crate::gizmo::{Gizmo, GizmoMode, GizmoSpace};
+use crate::renderer::RenderSettings;
+use crate::sampler::SamplePattern;
+use crate::tonemap::ToneMapper;
+
+pub fn color_edit(ui: &mut egui::Ui, label: &str, color: &mut glam::Vec3) -> bool {
+    ui.horizontal(|ui| {
+        let mut rgb = color.to_array();
+        let changed = ui.color_edit_button_rgb(&mut rgb).changed();
+        ui.label(label);
+
+        *color = glam::Vec3::from_array(rgb);
+        changed
+    })
+    .inner
+}
+
+pub fn vec3_edit(ui: &mut egui::Ui, label: &str, value: &mut glam::Vec3, speed: f32) -> bool {
+    ui.horizontal(|ui| {
+        let mut changed = false;
+
+        for (axis, name) in ["x", "y", "z"].into_iter().enumerate() {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut value[axis])
+                        .speed(speed)
+                        .prefix(format!("{name} ")),
+                )
+                .changed();
+        }
+
+        ui.label(label);
+        changed
+    })
+    .inner
+}
+
+// returns true if anything that affects the rendered image changed
+pub fn background_settings(ui: &mut egui::Ui, background: &mut Background) -> bool {
+    let mut changed = false;
+
+    let mode_name = |mode: &BackgroundMode| match mode {
+        BackgroundMode::Solid { .. } => "Solid colour",
+        BackgroundMode::Gradient { .. } => "Gradient",
+        BackgroundMode::Sky(_) => "Physical sky",
+        BackgroundMode::Environment(_) => "Environment map",
+    };
+
+    let modes = [
+        BackgroundMode::Solid {
+            color: glam::Vec3::ZERO,
+        },
+        Background::default().mode,
+        BackgroundMode::Sky(PhysicalSky::new(glam::vec3(0.3, 0.6, -0.7), 3.0)),
+    ];
+
+    egui::ComboBox::from_label("Mode")
+        .selected_text(mode_name(&background.mode))
+        .show_ui(ui, |ui| {
+            for mode in modes {
+                let selected = mode_name(&mode) == mode_name(&background.mode);
+                if ui.selectable_label(selected, mode_name(&mode)).clicked() && !selected {
+                    background.mode = mode;
+                    changed = true;
+                }
+            }
+        });
+
+    match &mut background.mode {
+        BackgroundMode::Solid { color } => {
+            changed |= color_edit(ui, "Colour", color);
+        }
+        BackgroundMode::Gradient {
+            zenith,
+            horizon,
+            ground,
+        } => {
+            changed |= color_edit(ui, "Zenith", zenith);
+            changed |= color_edit(ui, "Horizon", horizon);
+            changed |= color_edit(ui, "Ground", ground);
+        }
+        BackgroundMode::Sky(sky) => {
+            let sun = sky.get_sun_direction();
+            let mut elevation = sun.y.asin().to_degrees();
+            let mut azimuth = sun.x.atan2(sun.z).to_degrees();
+            let mut turbidity = sky.get_turbidity();
+
+            let sky_changed = ui
+                .add(egui::Slider::new(&mut elevation, -10.0..=90.0).text("Sun elevation"))
+                .changed()
+                | ui.add(egui::Slider::new(&mut azimuth, -180.0..=180.0).text("Sun azimuth"))
+                    .changed()
+                | ui.add(egui::Slider::new(&mut turbidity, 1.7..=10.0).text("Turbidity"))
+                    .changed();
+
+            if sky_changed {
+                let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+                let sun_direction = glam::vec3(
+                    elevation.cos() * azimuth.sin(),
+                    elevation.sin(),
+                    elevation.cos() * azimuth.cos(),
+                );
+
+                *sky = PhysicalSky::new(sun_direction, turbidity);
+                changed = true;
+            }
+        }
+        BackgroundMode::Environment(map) => {
+            let (width, height) = map.get_size();
+            ui.label(format!("Size: {width}x{height}"));
+        }
+    }
+
+    changed |= environment_loader(ui, background);
+
+    ui.separator();
+
+    let mut rotation = background.rotation.to_degrees();
+    if ui
+        .add(egui::Slider::new(&mut rotation, -180.0..=180.0).text("Rotation"))
+        .changed()
+    {
+        background.rotation = rotation.to_radians();
+        changed = true;
+    }
+
+    changed |= ui
+        .add(egui::Slider::new(&mut background.intensity, 0.0..=10.0).text("Intensity"))
+        .changed();
+
+    changed
+}
+
+fn environment_loader(ui: &mut egui::Ui, background: &mut Background) -> bool {
+    let path_id = ui.id().with("environment_path");
+    let error_id = ui.id().with("environment_error");
+
+    let mut path = ui
+        .data_mut(|d| d.get_temp::<String>(path_id))
+        .unwrap_or_default();
+
+    let mut error = ui.data_mut(|d| d.get_temp::<String>(error_id));
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut path).hint_text(".hdr or .exr file"));
+
+        if ui.button("Load").clicked() {
+            match EnvironmentMap::from_file(&path) {
+                Ok(map) => {
+                    background.mode = BackgroundMode::Environment(Arc::new(map));
+                    error = None;
+                    changed = true;
+                }
+                Err(e) => error = Some(e.to_string()),
+            }
+        }
+    });
+
+    if let Some(error) = &error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    ui.data_mut(|d| {
+        d.insert_temp(path_id, path);
+        match error {
+            Some(error) => d.insert_temp(error_id, error),
+            None => d.remove::<String>(error_id),
+        }
+    });
+
+    changed
+}
+
+// a physical camera derives the aperture radius itself
+pub fn lens_settings(ui: &mut egui::Ui, lens: &mut Lens, physical: bool) -> bool {
+    let mut changed = false;
+
+    changed |= ui
+        .add_enabled(
+            !physical,
+            egui::Slider::new(&mut lens.aperture_radius, 0.0..=0.5)
+                .text("Aperture radius")
+                .logarithmic(true),
+        )
+        .changed();
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut lens.focal_distance, 0.1..=100.0)
+                .text("Focal distance")
+                .logarithmic(true),
+        )
+        .changed();
+
+    let mut circular = lens.blades < 3;
+    ui.horizontal(|ui| {
+        ui.label("Aperture");
+        changed |= ui.radio_value(&mut circular, true, "Circular").changed();
+        changed |= ui.radio_value(&mut circular, false, "Polygonal").changed();
+    });
+
+    if circular {
+        lens.blades = 0;
+    } else {
+        lens.blades = lens.blades.max(3);
+
+        changed |= ui
+            .add(egui::Slider::new(&mut lens.blades, 3..=12).text("Blades"))
+            .changed();
+
+        let mut rotation = lens.blade_rotation.to_degrees();
+        if ui
+            .add(egui::Slider::new(&mut rotation, 0.0..=180.0).text("Blade rotation"))
+            .changed()
+        {
+            lens.blade_rotation = rotation.to_radians();
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+pub fn up_direction_edit(ui: &mut egui::Ui, up: &mut glam::Vec3) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Up");
+
+        for (name, preset) in [("+Y", glam::Vec3::Y), ("+Z", glam::Vec3::Z)] {
+            if ui.selectable_label(*up == preset, name).clicked() {
+                *up = preset;
+                changed = true;
+            }
+        }
+
+        for axis in 0..3 {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut up[axis])
+                        .speed(0.01)
+                        .range(-1.0..=1.0),
+                )
+                .changed();
+        }
+    });
+
+    changed
+}
+
+pub fn physical_camera_settings(ui: &mut egui::Ui, physical: &mut PhysicalCamera) -> bool {
+    let mut changed = false;
+
+    let sensors = [
+        ("Full frame", 36.0, 24.0),
+        ("APS-C", 23.6, 15.6),
+        ("Micro Four Thirds", 17.3, 13.0),
+        ("Super 35", 24.89, 18.66),
+    ];
+
+    let selected = sensors
+        .iter()
+        .find(|(_, w, h)| *w == physical.sensor_width && *h == physical.sensor_height)
+        .map_or("Custom", |(name, _, _)| name);
+
+    egui::ComboBox::from_label("Sensor")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (name, width, height) in sensors {
+                if ui.selectable_label(selected == name, name).clicked() {
+                    physical.sensor_width = width;
+                    physical.sensor_height = height;
+                    changed = true;
+                }
+            }
+        });
+
+    ui.horizontal(|ui| {
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut physical.sensor_width)
+                    .range(1.0..=100.0)
+                    .speed(0.1)
+                    .suffix(" mm"),
+            )
+            .changed();
+        ui.label("x");
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut physical.sensor_height)
+                    .range(1.0..=100.0)
+                    .speed(0.1)
+                    .suffix(" mm"),
+            )
+            .changed();
+    });
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut physical.focal_length, 4.0..=800.0)
+                .text("Focal length")
+                .suffix(" mm")
+                .logarithmic(true),
+        )
+        .changed();
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut physical.f_number, 0.95..=32.0)
+                .text("f-number")
+                .prefix("f/")
+                .logarithmic(true),
+        )
+        .changed();
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut physical.shutter_speed, 1.0 / 8000.0..=30.0)
+                .text("Shutter speed")
+                .logarithmic(true)
+                .custom_formatter(|t, _| {
+                    if t < 1.0 {
+                        format!("1/{:.0} s", 1.0 / t)
+                    } else {
+                        format!("{t:.1} s")
+                    }
+                }),
+        )
+        .changed();
+
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut physical.iso, 50.0..=102400.0)
+                .text("ISO")
+                .logarithmic(true)
+                .fixed_decimals(0),
+        )
+        .changed();
+
+    ui.label(format!(
+        "EV100 {:.1}, vertical fov {:.1}°",
+        physical.get_ev100(),
+        physical.get_vfov().to_degrees()
+    ));
+
+    changed
+}
+
+pub fn stereo_settings(ui: &mut egui::Ui, stereo: &mut Stereo) -> bool {
+    let mut changed = false;
+
+    egui::ComboBox::from_label("Stereo")
+        .selected_text(stereo.mode.name())
+        .show_ui(ui, |ui| {
+            for mode in StereoMode::ALL {
+                changed |= ui
+                    .selectable_value(&mut stereo.mode, mode, mode.name())
+                    .changed();
+            }
+        });
+
+    if stereo.mode != StereoMode::Off {
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut stereo.interocular_distance, 0.0..=1.0)
+                    .text("Interocular distance")
+                    .logarithmic(true),
+            )
+            .changed();
+
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut stereo.convergence_distance, 0.1..=100.0)
+                    .text("Convergence distance")
+                    .logarithmic(true),
+            )
+            .changed();
+    }
+
+    changed
+}
+
+pub fn shutter_settings(ui: &mut egui::Ui, shutter: &mut Shutter) -> bool {
+    let mut changed = false;
+
+    changed |= ui
+        .add(egui::Slider::new(&mut shutter.open, 0.0..=1.0).text("Shutter open"))
+        .changed();
+
+    changed |= ui
+        .add(egui::Slider::new(&mut shutter.close, 0.0..=1.0).text("Shutter close"))
+        .changed();
+
+    // keep the interval ordered whichever end was dragged past the other
+    if shutter.close < shutter.open {
+        std::mem::swap(&mut shutter.open, &mut shutter.close);
+    }
+
+    changed
+}
+
+pub fn projection_settings(ui: &mut egui::Ui, projection: &mut Projection) -> bool {
+    let mut changed = false;
+
+    let modes = [
+        Projection::Perspective,
+        Projection::Orthographic { view_height: 4.0 },
+        Projection::Fisheye {
+            fov: 180.0_f32.to_radians(),
+            mapping: FisheyeMapping::Equidistant,
+        },
+        Projection::Equirectangular,
+        Projection::Cylindrical {
+            horizontal_fov: 360.0_f32.to_radians(),
+        },
+    ];
+
+    egui::ComboBox::from_label("Projection")
+        .selected_text(projection.name())
+        .show_ui(ui, |ui| {
+            for mode in modes {
+                let selected = mode.name() == projection.name();
+                if ui.selectable_label(selected, mode.name()).clicked() && !selected {
+                    *projection = mode;
+                    changed = true;
+                }
+            }
+        });
+
+    let mut angle_slider = |ui: &mut egui::Ui, angle: &mut f32, max: f32, text: &str| {
+        let mut degrees = angle.to_degrees();
+        if ui
+            .add(egui::Slider::new(&mut degrees, 1.0..=max).text(text))
+            .changed()
+        {
+            *angle = degrees.to_radians();
+            changed = true;
+        }
+    };
+
+    match projection {
+        Projection::Perspective | Projection::Equirectangular => {}
+        Projection::Orthographic { view_height } => {
+            changed |= ui
+                .add(
+                    egui::Slider::new(view_height, 0.1..=100.0)
+                        .text("View height")
+                        .logarithmic(true),
+                )
+                .changed();
+        }
+        Projection::Fisheye { fov, mapping } => {
+            angle_slider(ui, fov, 360.0, "Field of view");
+
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .radio_value(mapping, FisheyeMapping::Equidistant, "Equidistant")
+                    .changed();
+                changed |= ui
+                    .radio_value(mapping, FisheyeMapping::Equisolid, "Equisolid")
+                    .changed();
+            });
+        }
+        Projection::Cylindrical { horizontal_fov } => {
+            angle_slider(ui, horizontal_fov, 360.0, "Horizontal field of view");
+        }
+    }
+
+    changed
+}
+
+pub fn controls_settings(ui: &mut egui::Ui, controls: &mut Controls) {
+    ui.add(
+        egui::Slider::new(&mut controls.move_speed, 0.01..=1000.0)
+            .text("Move speed")
+            .logarithmic(true),
+    );
+
+    let mut sensitivity = controls.mouse_sensitivity * 1000.0;
+    if ui
+        .add(egui::Slider::new(&mut sensitivity, 0.05..=5.0).text("Mouse sensitivity"))
+        .changed()
+    {
+        controls.mouse_sensitivity = sensitivity / 1000.0;
+    }
+
+    ui.add(
+        egui::Slider::new(&mut controls.smoothing, 0.0..=0.5)
+            .text("Smoothing (s)")
+            .clamping(egui::SliderClamping::Always),
+    );
+    ui.checkbox(&mut controls.invert_y, "Invert Y");
+
+    ui.label("Shift moves faster, Ctrl slower, scroll while flying changes the speed");
+
+    // the action waiting for a key press, if any
+    let listening_id = ui.id().with("listening");
+    let mut listening = ui.data(|d| d.get_temp::<usize>(listening_id));
+
+    egui::Grid::new("key_bindings").show(ui, |ui| {
+        for (index, action) in Action::ALL.into_iter().enumerate() {
+            ui.label(action.name());
+
+            if listening == Some(index) {
+                let pressed = ui.input(|i| {
+                    i.events.iter().find_map(|event| match event {
+                        egui::Event::Key {
+                            key, pressed: true, ..
+                        } => Some(*key),
+                        _ => None,
+                    })
+                });
+
+                match pressed {
+                    Some(egui::Key::Escape) => listening = None,
+                    Some(key) => {
+                        controls.bindings.set(action, key);
+                        listening = None;
+                    }
+                    None => {
+                        ui.label("Press a key...");
+                    }
+                }
+            }
+
+            if listening != Some(index) && ui.button(controls.bindings.get(action).name()).clicked()
+            {
+                listening = Some(index);
+            }
+
+            ui.end_row();
+        }
+    });
+
+    ui.data_mut(|d| match listening {
+        Some(index) => d.insert_temp(listening_id, index),
+        None => d.remove::<usize>(listening_id),
+    });
+
+    if ui.button("Reset to defaults").clicked() {
+        *controls = Controls::default();
+    }
+}
+
+pub fn gizmo_settings(ui: &mut egui::Ui, gizmo: &mut Gizmo) {
+    ui.horizontal(|ui| {
+        for mode in GizmoMode::ALL {
+            ui.selectable_value(&mut gizmo.mode, mode, mode.name());
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Space");
+        ui.radio_value(&mut gizmo.space, GizmoSpace::World, "World");
+        ui.radio_value(&mut gizmo.space, GizmoSpace::Local, "Local");
+    });
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut gizmo.snapping, "Snap");
+
+        ui.add_enabled_ui(gizmo.snapping, |ui| match gizmo.mode {
+            GizmoMode::Translate => {
+                ui.add(
+                    egui::DragValue::new(&mut gizmo.translate_step)
+                        .speed(0.01)
+                        .range(0.01..=10.0)
+                        .suffix(" grid"),
+                );
+            }
+            GizmoMode::Rotate => {
+                ui.drag_angle(&mut gizmo.rotate_step);
+                gizmo.rotate_step = gizmo
+                    .rotate_step
+                    .clamp(1.0_f32.to_radians(), 90.0_f32.to_radians());
+            }
+            GizmoMode::Scale => {
+                ui.add(
+                    egui::DragValue::new(&mut gizmo.scale_step)
+                        .speed(0.01)
+                        .range(0.01..=10.0)
+                        .suffix(" radius"),
+                );
+            }
+        });
+    });
+}
+
+pub fn render_settings(ui: &mut egui::Ui, settings: &mut RenderSettings) {
+    ui.checkbox(&mut settings.accumulate, "Accumulate");
+
+    ui.add(egui::Slider::new(&mut settings.samples_per_pixel, 1..=64).text("Samples per pixel"));
+    ui.add(egui::Slider::new(&mut settings.max_bounces, 0..=32).text("Max bounces"));
+
+    ui.checkbox(
+        &mut settings.reprojection,
+        "Reproject when the camera moves",
+    );
+
+    ui.add(
+        egui::Slider::new(&mut settings.resolution_scale, 0.1..=1.0)
+            .text("Resolution scale")
+            .fixed_decimals(2),
+    );
+
+    // the resolution scale above is where the image refines to
+    ui.checkbox(&mut settings.adaptive_resolution, "Adaptive resolution");
+    ui.add_enabled(
+        settings.adaptive_resolution,
+        egui::Slider::new(&mut settings.target_fps, 5.0..=120.0)
+            .text("Target FPS")
+            .fixed_decimals(0),
+    );
+
+    egui::ComboBox::from_label("Sample pattern")
+        .selected_text(settings.sample_pattern.name())
+        .show_ui(ui, |ui| {
+            for pattern in SamplePattern::ALL {
+                ui.selectable_value(&mut settings.sample_pattern, pattern, pattern.name());
+            }
+        });
+
+    egui::ComboBox::from_label("Filter")
+        .selected_text(settings.filter.name())
+        .show_ui(ui, |ui| {
+            for filter in Filter::ALL {
+                ui.selectable_value(&mut settings.filter, filter, filter.name());
+            }
+        });
+
+    ui.separator();
+
+    egui::ComboBox::from_label("Tone mapper")
+        .selected_text(settings.tone_mapper.name())
+        .show_ui(ui, |ui| {
+            for mapper in ToneMapper::ALL {
+                ui.selectable_value(&mut settings.tone_mapper, mapper, mapper.name());
+            }
+        });
+
+    ui.add(egui::Slider::new(&mut settings.exposure, -10.0..=10.0).text("Exposure (EV)"));
+
+    egui::ComboBox::from_label("Debug view")
+        .selected_text(settings.debug_view.name())
+        .show_ui(ui, |ui| {
+            for view in DebugView::ALL {
+                ui.selectable_value(&mut settings.debug_view, view, view.name());
+            }
+        });
+    ui.add_enabled_ui(settings.debug_view == DebugView::Beauty, |ui| {
+        // exported as exr layers next to the frames of the camera path
+        ui.checkbox(&mut settings.aovs, "Render AOVs");
+
+        ui.checkbox(&mut settings.denoise, "Denoise");
+        ui.add_enabled(
+            settings.denoise,
+            egui::Slider::new(&mut settings.denoise_strength, 0.0..=1.0).text("Denoise strength"),
+        );
+    });
+}
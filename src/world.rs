@@ -1,21 +1,232 @@
-#[derive(Debug, Clone, Copy)]
+use crate::background::Background;
+use crate::camera::{Bookmark, Viewpoint};
+use crate::camera_path::CameraPath;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampling;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Sphere {
     pub position: glam::Vec3,
     pub radius: f32,
-    pub albedo: glam::Vec3,
+    pub material_index: usize,
+    // displacement over one frame interval, the sphere is at `position` at time 0
+    pub velocity: glam::Vec3,
+    // invisible on the surface, it orients the local axes of the gizmos
+    pub rotation: glam::Quat,
 }
 
 impl Sphere {
-    pub fn new(position: glam::Vec3, radius: f32, albedo: glam::Vec3) -> Self {
+    pub fn new(position: glam::Vec3, radius: f32, material_index: usize) -> Self {
         Self {
             position,
             radius,
-            albedo,
+            material_index,
+            velocity: glam::Vec3::ZERO,
+            rotation: glam::Quat::IDENTITY,
+        }
+    }
+
+    pub fn position_at(&self, time: f32) -> glam::Vec3 {
+        self.position + self.velocity * time
+    }
+
+    pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<f32> {
+        let origin = ray.origin - self.position_at(ray.time);
+        let a = ray.direction.dot(ray.direction);
+        let half_b = origin.dot(ray.direction);
+        let c = origin.dot(origin) - self.radius * self.radius;
+
+        // (b/2)^2 - ac
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None;
         }
+
+        let d_sqrt = discriminant.sqrt();
+
+        // closest hit first, the far one is only visible from inside the sphere
+        [(-half_b - d_sqrt) / a, (-half_b + d_sqrt) / a]
+            .into_iter()
+            .find(|&t| t > t_min && t < t_max)
+    }
+
+    // cosine of the half angle of the cone the sphere subtends from `point`,
+    // `None` if the point is inside the sphere
+    fn cone_cos_theta_max(&self, point: glam::Vec3, time: f32) -> Option<f32> {
+        let distance_squared = point.distance_squared(self.position_at(time));
+        let radius_squared = self.radius * self.radius;
+
+        if distance_squared <= radius_squared {
+            return None;
+        }
+
+        Some((1.0 - radius_squared / distance_squared).max(0.0).sqrt())
     }
+
+    pub fn sample_solid_angle(
+        &self,
+        point: glam::Vec3,
+        time: f32,
+        u: glam::Vec2,
+    ) -> Option<(glam::Vec3, f32)> {
+        let cos_theta_max = self.cone_cos_theta_max(point, time)?;
+        let axis = (self.position_at(time) - point).normalize();
+
+        Some((
+            sampling::uniform_cone(axis, cos_theta_max, u),
+            sampling::uniform_cone_pdf(cos_theta_max),
+        ))
+    }
+
+    pub fn solid_angle_pdf(&self, point: glam::Vec3, time: f32) -> f32 {
+        self.cone_cos_theta_max(point, time)
+            .map_or(0.0, sampling::uniform_cone_pdf)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Hit {
+    pub position: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub object_index: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct World {
     pub objects: Vec<Sphere>,
+    pub materials: Vec<Material>,
+    pub background: Background,
+    pub bookmarks: Vec<Bookmark>,
+    pub camera_path: CameraPath,
+}
+
+impl World {
+    // the scene both the viewer and the headless renderer start with
+    pub fn demo() -> Self {
+        let materials = vec![
+            Material::diffuse(glam::vec3(1.0, 0.0, 1.0)),
+            Material::diffuse(glam::vec3(1.0, 0.53, 0.0)),
+            Material::emissive(glam::vec3(1.0, 0.85, 0.6), 12.0),
+        ];
+
+        let objects = vec![
+            // moving, so the shutter has something to blur
+            Sphere {
+                velocity: glam::vec3(0.2, 0.0, 0.0),
+                ..Sphere::new(glam::vec3(0.0, 0.0, 0.0), 0.5, 0)
+            },
+            Sphere::new(glam::vec3(-0.2, -0.3, -3.0), 2.0, 1),
+            Sphere::new(glam::vec3(1.1, 0.8, 0.2), 0.3, 2),
+        ];
+
+        let vfov = 45.0_f32.to_radians();
+        let bookmarks = vec![
+            Bookmark {
+                name: "Front".to_string(),
+                viewpoint: Viewpoint {
+                    position: glam::vec3(0.0, 0.0, 3.0),
+                    forward_direction: glam::vec3(0.0, 0.0, -1.0),
+                    roll: 0.0,
+                    vfov,
+                },
+            },
+            Bookmark {
+                name: "Above".to_string(),
+                viewpoint: Viewpoint {
+                    position: glam::vec3(0.0, 4.0, 3.0),
+                    forward_direction: glam::vec3(0.0, -4.0, -3.0).normalize(),
+                    roll: 0.0,
+                    vfov,
+                },
+            },
+        ];
+
+        Self {
+            camera_path: CameraPath::turntable(glam::vec3(-0.2, -0.3, -3.0), 7.0, 1.5, vfov, 8.0),
+            objects,
+            materials,
+            background: Background::default(),
+            bookmarks,
+        }
+    }
+
+    pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let (t, object_index) = self
+            .objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, sphere)| Some((sphere.intersect(ray, t_min, t_max)?, i)))
+            .min_by(|(t1, _), (t2, _)| t1.total_cmp(t2))?;
+
+        let sphere = &self.objects[object_index];
+        let position = ray.origin + t * ray.direction;
+
+        Some(Hit {
+            position,
+            normal: (position - sphere.position_at(ray.time)) / sphere.radius,
+            object_index,
+        })
+    }
+
+    pub fn remove_object(&mut self, object_index: usize) {
+        self.objects.remove(object_index);
+    }
+
+    pub fn is_material_used(&self, material_index: usize) -> bool {
+        self.objects
+            .iter()
+            .any(|sphere| sphere.material_index == material_index)
+    }
+
+    // only unused materials can go, returns whether it was removed
+    pub fn remove_material(&mut self, material_index: usize) -> bool {
+        if self.is_material_used(material_index) {
+            return false;
+        }
+
+        self.materials.remove(material_index);
+
+        for sphere in &mut self.objects {
+            if sphere.material_index > material_index {
+                sphere.material_index -= 1;
+            }
+        }
+
+        true
+    }
+
+    pub fn get_material(&self, object_index: usize) -> &Material {
+        &self.materials[self.objects[object_index].material_index]
+    }
+
+    // bounding sphere of all the objects, `None` for an empty scene
+    pub fn get_bounds(&self) -> Option<(glam::Vec3, f32)> {
+        if self.objects.is_empty() {
+            return None;
+        }
+
+        let (min, max) = self.objects.iter().fold(
+            (glam::Vec3::INFINITY, glam::Vec3::NEG_INFINITY),
+            |(min, max), sphere| {
+                (
+                    min.min(sphere.position - sphere.radius),
+                    max.max(sphere.position + sphere.radius),
+                )
+            },
+        );
+
+        let center = (min + max) / 2.0;
+        let radius = self
+            .objects
+            .iter()
+            .map(|sphere| sphere.position.distance(center) + sphere.radius)
+            .fold(0.0, f32::max);
+
+        Some((center, radius))
+    }
+
+    pub fn get_emissive_objects(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.objects.len()).filter(|&i| self.get_material(i).is_emissive())
+    }
 }